@@ -67,9 +67,10 @@ fn main() -> ! {
     };
 
     // GPS1 port USART1:
+    // u-blox M8 modules use 9600 baud until configured otherwise
     let usart1_port = {
         let config =
-            p_hal::serial::config::Config::default().baudrate(115200.bps());
+            p_hal::serial::config::Config::default().baudrate(9600.bps());
         let rx = gpiob.pb7.into_alternate_af7();
         let tx = gpiob.pb6.into_alternate_af7();
         dp.USART1
@@ -80,7 +81,13 @@ fn main() -> ! {
 
     let (mut console_tx, mut _console_rx) = uart7_port.split();
     let mut driver = ublox::new_serial_driver(usart1_port);
-    driver.setup(&mut delay_source).unwrap();
+    if let Err(err) = driver.setup(&mut delay_source) {
+        // the module may already be configured: carry on regardless
+        console_print(
+            &mut console_tx,
+            format_args!(">>> setup failed: {:?} \r\n", err),
+        );
+    }
 
     loop {
         let rc = driver.handle_one_message();
@@ -0,0 +1,1955 @@
+/*
+Copyright (c) 2020 Todd Stellanova
+LICENSE: BSD3 (see LICENSE file)
+*/
+
+//! Typed UBX-CFG configuration messages
+
+use crate::messages::*;
+
+/// I/O ports (targets) of the device
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PortId {
+    /// DDC (I2C compatible) port
+    Ddc = 0,
+    Uart1 = 1,
+    Uart2 = 2,
+    Usb = 3,
+    Spi = 4,
+}
+
+impl PortId {
+    pub fn from_u8(port_id: u8) -> Option<Self> {
+        match port_id {
+            0 => Some(Self::Ddc),
+            1 => Some(Self::Uart1),
+            2 => Some(Self::Uart2),
+            3 => Some(Self::Usb),
+            4 => Some(Self::Spi),
+            _ => None,
+        }
+    }
+}
+
+/// Number of I/O targets in UBX-CFG-MSG (including one reserved target)
+pub const UBX_CFG_MSG_NUM_TARGETS: usize = 6;
+
+/// UBX-CFG-MSG message: Set message rate(s) on each I/O target.
+/// See 32.10.18 UBX-CFG-MSG (0x06 0x01)
+/// Each rate is the number of navigation solutions per message sent,
+/// so 1 means once per navigation epoch, and zero disables the message.
+#[derive(Copy, Clone, Debug)]
+pub struct CfgMsg {
+    /// Class and ID of the message to configure, eg `UBX_MSG_ID_NAV_PVT`
+    pub msg_unique_id: u16,
+    /// Rate on each I/O target, indexed by `PortId`
+    pub rates: [u8; UBX_CFG_MSG_NUM_TARGETS],
+}
+
+impl CfgMsg {
+    /// Send the message at the same rate on all I/O targets
+    pub fn new(msg_unique_id: u16, rate: u8) -> Self {
+        Self {
+            msg_unique_id,
+            rates: [rate; UBX_CFG_MSG_NUM_TARGETS],
+        }
+    }
+
+    /// Disable the message on all I/O targets
+    pub fn disabled(msg_unique_id: u16) -> Self {
+        Self::new(msg_unique_id, 0)
+    }
+
+    /// Change the rate on a single I/O target
+    pub fn with_rate(mut self, port: PortId, rate: u8) -> Self {
+        self.rates[port as usize] = rate;
+        self
+    }
+}
+
+pub const UBX_MSG_LEN_CFG_MSG: usize = 8;
+
+impl UbxCommand for CfgMsg {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_MSG;
+
+    fn write_payload(&self, buf: &mut [u8]) -> Option<usize> {
+        if buf.len() < UBX_MSG_LEN_CFG_MSG {
+            return None;
+        }
+        buf[0] = (self.msg_unique_id >> 8) as u8;
+        buf[1] = (self.msg_unique_id & 0xFF) as u8;
+        buf[2..UBX_MSG_LEN_CFG_MSG].copy_from_slice(&self.rates);
+        Some(UBX_MSG_LEN_CFG_MSG)
+    }
+}
+
+/// UBX-CFG-MSG message: Set message rate on the port this is sent to.
+/// See 32.10.18 UBX-CFG-MSG (0x06 0x01)
+#[derive(Copy, Clone, Debug)]
+pub struct CfgMsgCurrentPort {
+    /// Class and ID of the message to configure, eg `UBX_MSG_ID_NAV_PVT`
+    pub msg_unique_id: u16,
+    /// Navigation solutions per message sent, or zero to disable
+    pub rate: u8,
+}
+
+pub const UBX_MSG_LEN_CFG_MSG_CURRENT_PORT: usize = 3;
+
+impl UbxCommand for CfgMsgCurrentPort {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_MSG;
+
+    fn write_payload(&self, buf: &mut [u8]) -> Option<usize> {
+        if buf.len() < UBX_MSG_LEN_CFG_MSG_CURRENT_PORT {
+            return None;
+        }
+        buf[0] = (self.msg_unique_id >> 8) as u8;
+        buf[1] = (self.msg_unique_id & 0xFF) as u8;
+        buf[2] = self.rate;
+        Some(UBX_MSG_LEN_CFG_MSG_CURRENT_PORT)
+    }
+}
+
+/// UBX protocol, in UBX-CFG-PRT protocol masks
+pub const PROTO_MASK_UBX: u16 = 0x0001;
+/// NMEA protocol, in UBX-CFG-PRT protocol masks
+pub const PROTO_MASK_NMEA: u16 = 0x0002;
+/// RTCM2 protocol (input only), in UBX-CFG-PRT protocol masks
+pub const PROTO_MASK_RTCM2: u16 = 0x0004;
+/// RTCM3 protocol, in UBX-CFG-PRT protocol masks
+pub const PROTO_MASK_RTCM3: u16 = 0x0020;
+
+/// UART mode: 8 data bits, no parity, 1 stop bit
+pub const UART_MODE_8N1: u32 = 0x0000_08D0;
+
+/// UBX-CFG-PRT message: Port Configuration.
+/// See 32.10.25 UBX-CFG-PRT (0x06 0x00)
+/// Polling with an empty payload returns the configuration of the port
+/// the poll was received on.
+#[derive(Copy, Clone, Debug)]
+pub struct CfgPrt {
+    pub port_id: PortId, //0 portID - Port identifier number
+    pub tx_ready: u16,   //2 txReady - TX ready PIN configuration
+    /// 4 mode - Port mode: its layout depends on the type of port,
+    /// see `uart`, `spi` and `ddc`
+    pub mode: u32,
+    pub baud_rate: u32, //8 baudRate - Baud rate, UART ports only (bits/s)
+    pub in_proto_mask: u16, //12 inProtoMask - Protocols accepted, see PROTO_MASK_*
+    pub out_proto_mask: u16, //14 outProtoMask - Protocols sent, see PROTO_MASK_*
+    pub flags: u16,          //16 flags - bit 1: extendedTxTimeout
+}
+
+impl CfgPrt {
+    /// Configure a UART port for 8N1 at the given baud rate
+    pub fn uart(
+        port_id: PortId,
+        baud_rate: u32,
+        in_proto_mask: u16,
+        out_proto_mask: u16,
+    ) -> Self {
+        Self {
+            port_id,
+            tx_ready: 0,
+            mode: UART_MODE_8N1,
+            baud_rate,
+            in_proto_mask,
+            out_proto_mask,
+            flags: 0,
+        }
+    }
+
+    /// Configure the SPI port.
+    /// `spi_mode` is the SPI clock polarity and phase mode (0-3), and
+    /// `ff_count` is the number of 0xFF bytes the device will receive
+    /// before it stops receiving (0 to disable, up to 63)
+    pub fn spi(
+        spi_mode: u8,
+        ff_count: u8,
+        in_proto_mask: u16,
+        out_proto_mask: u16,
+    ) -> Self {
+        Self {
+            port_id: PortId::Spi,
+            tx_ready: 0,
+            mode: ((spi_mode as u32 & 0x03) << 1)
+                | ((ff_count as u32 & 0x3F) << 8),
+            baud_rate: 0,
+            in_proto_mask,
+            out_proto_mask,
+            flags: 0,
+        }
+    }
+
+    /// Configure the DDC (I2C) port with the given 7-bit address
+    pub fn ddc(address: u8, in_proto_mask: u16, out_proto_mask: u16) -> Self {
+        Self {
+            port_id: PortId::Ddc,
+            tx_ready: 0,
+            mode: (address as u32 & 0x7F) << 1,
+            baud_rate: 0,
+            in_proto_mask,
+            out_proto_mask,
+            flags: 0,
+        }
+    }
+
+    /// Configure the USB port
+    pub fn usb(in_proto_mask: u16, out_proto_mask: u16) -> Self {
+        Self {
+            port_id: PortId::Usb,
+            tx_ready: 0,
+            mode: 0,
+            baud_rate: 0,
+            in_proto_mask,
+            out_proto_mask,
+            flags: 0,
+        }
+    }
+
+    /// UART ports: number of data bits (5-8)
+    pub fn uart_char_len(&self) -> u8 {
+        5 + ((self.mode >> 6) & 0x03) as u8
+    }
+
+    /// UART ports: parity (0b000 even, 0b001 odd, 0b10x none)
+    pub fn uart_parity(&self) -> u8 {
+        ((self.mode >> 9) & 0x07) as u8
+    }
+
+    /// UART ports: stop bits (0 = 1, 1 = 1.5, 2 = 2, 3 = 0.5)
+    pub fn uart_stop_bits(&self) -> u8 {
+        ((self.mode >> 12) & 0x03) as u8
+    }
+
+    /// SPI port: clock polarity and phase mode (0-3)
+    pub fn spi_mode(&self) -> u8 {
+        ((self.mode >> 1) & 0x03) as u8
+    }
+
+    /// SPI port: number of 0xFF bytes received before reception stops
+    pub fn spi_ff_count(&self) -> u8 {
+        ((self.mode >> 8) & 0x3F) as u8
+    }
+
+    /// DDC port: 7-bit I2C address
+    pub fn ddc_address(&self) -> u8 {
+        ((self.mode >> 1) & 0x7F) as u8
+    }
+}
+
+pub const UBX_MSG_LEN_CFG_PRT: usize = 20;
+pub fn cfg_prt_from_bytes(buf: &[u8]) -> Option<CfgPrt> {
+    if buf.len() < UBX_MSG_LEN_CFG_PRT {
+        return None;
+    }
+    Some(CfgPrt {
+        port_id: PortId::from_u8(buf[0])?,
+        tx_ready: read_u16(buf, 2),
+        mode: read_u32(buf, 4),
+        baud_rate: read_u32(buf, 8),
+        in_proto_mask: read_u16(buf, 12),
+        out_proto_mask: read_u16(buf, 14),
+        flags: read_u16(buf, 16),
+    })
+}
+
+impl UbxMessage for CfgPrt {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_PRT;
+
+    fn from_payload(buf: &[u8]) -> Option<Self> {
+        cfg_prt_from_bytes(buf)
+    }
+}
+
+impl UbxCommand for CfgPrt {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_PRT;
+
+    fn write_payload(&self, buf: &mut [u8]) -> Option<usize> {
+        if buf.len() < UBX_MSG_LEN_CFG_PRT {
+            return None;
+        }
+        buf[..UBX_MSG_LEN_CFG_PRT].fill(0);
+        buf[0] = self.port_id as u8;
+        write_u16(buf, 2, self.tx_ready);
+        write_u32(buf, 4, self.mode);
+        write_u32(buf, 8, self.baud_rate);
+        write_u16(buf, 12, self.in_proto_mask);
+        write_u16(buf, 14, self.out_proto_mask);
+        write_u16(buf, 16, self.flags);
+        Some(UBX_MSG_LEN_CFG_PRT)
+    }
+}
+
+/// Time system to which measurements are aligned, in UBX-CFG-RATE
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TimeRef {
+    Utc,
+    Gps,
+    Glonass,
+    BeiDou,
+    Galileo,
+    /// A time system not listed here, eg added by newer firmware
+    Other(u16),
+}
+
+impl TimeRef {
+    pub fn from_u16(time_ref: u16) -> Self {
+        match time_ref {
+            0 => Self::Utc,
+            1 => Self::Gps,
+            2 => Self::Glonass,
+            3 => Self::BeiDou,
+            4 => Self::Galileo,
+            _ => Self::Other(time_ref),
+        }
+    }
+
+    pub fn to_u16(self) -> u16 {
+        match self {
+            Self::Utc => 0,
+            Self::Gps => 1,
+            Self::Glonass => 2,
+            Self::BeiDou => 3,
+            Self::Galileo => 4,
+            Self::Other(time_ref) => time_ref,
+        }
+    }
+}
+
+/// UBX-CFG-RATE message: Navigation/measurement rate settings
+/// See 32.10.27 UBX-CFG-RATE (0x06 0x08)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CfgRate {
+    /// 0 measRate - Time between GNSS measurements (ms),
+    /// eg 100 for 10 Hz, 200 for 5 Hz
+    pub meas_rate_ms: u16,
+    /// 2 navRate - Number of measurements per navigation solution (max 127)
+    pub nav_rate: u16,
+    /// 4 timeRef - Time system measurements are aligned to
+    pub time_ref: TimeRef,
+}
+
+impl CfgRate {
+    /// One navigation solution per measurement, aligned to GPS time
+    pub fn new(meas_rate_ms: u16) -> Self {
+        Self {
+            meas_rate_ms,
+            nav_rate: 1,
+            time_ref: TimeRef::Gps,
+        }
+    }
+}
+
+pub const UBX_MSG_LEN_CFG_RATE: usize = 6;
+pub fn cfg_rate_from_bytes(buf: &[u8]) -> Option<CfgRate> {
+    if buf.len() < UBX_MSG_LEN_CFG_RATE {
+        return None;
+    }
+    Some(CfgRate {
+        meas_rate_ms: read_u16(buf, 0),
+        nav_rate: read_u16(buf, 2),
+        time_ref: TimeRef::from_u16(read_u16(buf, 4)),
+    })
+}
+
+impl UbxMessage for CfgRate {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_RATE;
+
+    fn from_payload(buf: &[u8]) -> Option<Self> {
+        cfg_rate_from_bytes(buf)
+    }
+}
+
+impl UbxCommand for CfgRate {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_RATE;
+
+    fn write_payload(&self, buf: &mut [u8]) -> Option<usize> {
+        if buf.len() < UBX_MSG_LEN_CFG_RATE {
+            return None;
+        }
+        write_u16(buf, 0, self.meas_rate_ms);
+        write_u16(buf, 2, self.nav_rate);
+        write_u16(buf, 4, self.time_ref.to_u16());
+        Some(UBX_MSG_LEN_CFG_RATE)
+    }
+}
+
+/// Dynamic platform model, in UBX-CFG-NAV5
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DynamicModel {
+    Portable,
+    Stationary,
+    Pedestrian,
+    Automotive,
+    Sea,
+    /// Airborne with < 1g acceleration
+    Airborne1g,
+    /// Airborne with < 2g acceleration
+    Airborne2g,
+    /// Airborne with < 4g acceleration
+    Airborne4g,
+    /// Wrist worn watch
+    Wrist,
+    Bike,
+    /// A model not listed here, eg added by newer firmware
+    Other(u8),
+}
+
+impl DynamicModel {
+    pub fn from_u8(dyn_model: u8) -> Self {
+        match dyn_model {
+            0 => Self::Portable,
+            2 => Self::Stationary,
+            3 => Self::Pedestrian,
+            4 => Self::Automotive,
+            5 => Self::Sea,
+            6 => Self::Airborne1g,
+            7 => Self::Airborne2g,
+            8 => Self::Airborne4g,
+            9 => Self::Wrist,
+            10 => Self::Bike,
+            _ => Self::Other(dyn_model),
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            Self::Portable => 0,
+            Self::Stationary => 2,
+            Self::Pedestrian => 3,
+            Self::Automotive => 4,
+            Self::Sea => 5,
+            Self::Airborne1g => 6,
+            Self::Airborne2g => 7,
+            Self::Airborne4g => 8,
+            Self::Wrist => 9,
+            Self::Bike => 10,
+            Self::Other(dyn_model) => dyn_model,
+        }
+    }
+}
+
+/// Position fixing mode, in UBX-CFG-NAV5
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FixMode {
+    Only2D = 1,
+    Only3D = 2,
+    Auto2D3D = 3,
+}
+
+impl FixMode {
+    pub fn from_u8(fix_mode: u8) -> Option<Self> {
+        match fix_mode {
+            1 => Some(Self::Only2D),
+            2 => Some(Self::Only3D),
+            3 => Some(Self::Auto2D3D),
+            _ => None,
+        }
+    }
+}
+
+/// UBX-CFG-NAV5 mask bits: which settings are applied
+pub const NAV5_MASK_DYN: u16 = 0x0001;
+pub const NAV5_MASK_MIN_EL: u16 = 0x0002;
+pub const NAV5_MASK_POS_FIX_MODE: u16 = 0x0004;
+pub const NAV5_MASK_DR_LIM: u16 = 0x0008;
+pub const NAV5_MASK_POS_MASK: u16 = 0x0010;
+pub const NAV5_MASK_TIME_MASK: u16 = 0x0020;
+pub const NAV5_MASK_STATIC_HOLD_MASK: u16 = 0x0040;
+pub const NAV5_MASK_DGPS_MASK: u16 = 0x0080;
+pub const NAV5_MASK_CNO_THRESHOLD: u16 = 0x0100;
+pub const NAV5_MASK_UTC: u16 = 0x0400;
+
+/// UBX-CFG-NAV5 message: Navigation Engine Settings
+/// See 32.10.19 UBX-CFG-NAV5 (0x06 0x24)
+/// Only the settings selected by `mask` are applied by the device:
+/// the `with_` builder methods set both the setting and its mask bit.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CfgNav5 {
+    pub mask: u16, //0 mask - Parameters bitmask, see NAV5_MASK_*
+    pub dyn_model: DynamicModel, //2 dynModel - Dynamic platform model
+    pub fix_mode: FixMode, //3 fixMode - Position fixing mode
+    pub fixed_alt: i32, //4 fixedAlt - Fixed altitude (mean sea level) for 2D fix mode (0.01 m)
+    pub fixed_alt_var: u32, //8 fixedAltVar - Fixed altitude variance for 2D mode (0.0001 m^2)
+    pub min_elev: i8, //12 minElev - Minimum elevation for a GNSS satellite to be used (deg)
+    pub dr_limit: u8, //13 drLimit - Reserved
+    pub p_dop: u16,   //14 pDop - Position DOP mask to use (0.1)
+    pub t_dop: u16,   //16 tDop - Time DOP mask to use (0.1)
+    pub p_acc: u16,   //18 pAcc - Position accuracy mask (m)
+    pub t_acc: u16,   //20 tAcc - Time accuracy mask (m)
+    pub static_hold_thresh: u8, //22 staticHoldThresh - Static hold threshold (cm/s)
+    pub dgnss_timeout: u8,      //23 dgnssTimeout - DGNSS timeout (s)
+    pub cno_thresh_num_svs: u8, //24 cnoThreshNumSVs - Number of satellites required above cnoThresh for a fix
+    pub cno_thresh: u8, //25 cnoThresh - C/N0 threshold for deciding whether to attempt a fix (dBHz)
+    pub static_hold_max_dist: u16, //28 staticHoldMaxDist - Static hold distance threshold (m)
+    pub utc_standard: u8,          //30 utcStandard - UTC standard to be used
+}
+
+impl Default for CfgNav5 {
+    /// No settings selected by the mask
+    fn default() -> Self {
+        Self {
+            mask: 0,
+            dyn_model: DynamicModel::Portable,
+            fix_mode: FixMode::Auto2D3D,
+            fixed_alt: 0,
+            fixed_alt_var: 0,
+            min_elev: 0,
+            dr_limit: 0,
+            p_dop: 0,
+            t_dop: 0,
+            p_acc: 0,
+            t_acc: 0,
+            static_hold_thresh: 0,
+            dgnss_timeout: 0,
+            cno_thresh_num_svs: 0,
+            cno_thresh: 0,
+            static_hold_max_dist: 0,
+            utc_standard: 0,
+        }
+    }
+}
+
+impl CfgNav5 {
+    pub fn with_dyn_model(mut self, dyn_model: DynamicModel) -> Self {
+        self.dyn_model = dyn_model;
+        self.mask |= NAV5_MASK_DYN;
+        self
+    }
+
+    /// Position fixing mode, with the fixed altitude (0.01 m) and its
+    /// variance (0.0001 m^2) used for 2D fixes: the device applies
+    /// all three together.
+    pub fn with_fix_mode(
+        mut self,
+        fix_mode: FixMode,
+        fixed_alt: i32,
+        fixed_alt_var: u32,
+    ) -> Self {
+        self.fix_mode = fix_mode;
+        self.fixed_alt = fixed_alt;
+        self.fixed_alt_var = fixed_alt_var;
+        self.mask |= NAV5_MASK_POS_FIX_MODE;
+        self
+    }
+
+    /// Minimum satellite elevation (deg)
+    pub fn with_min_elev(mut self, min_elev: i8) -> Self {
+        self.min_elev = min_elev;
+        self.mask |= NAV5_MASK_MIN_EL;
+        self
+    }
+
+    /// Position DOP mask (0.1) and position accuracy mask (m)
+    pub fn with_pos_masks(mut self, p_dop: u16, p_acc: u16) -> Self {
+        self.p_dop = p_dop;
+        self.p_acc = p_acc;
+        self.mask |= NAV5_MASK_POS_MASK;
+        self
+    }
+
+    /// Time DOP mask (0.1) and time accuracy mask (m)
+    pub fn with_time_masks(mut self, t_dop: u16, t_acc: u16) -> Self {
+        self.t_dop = t_dop;
+        self.t_acc = t_acc;
+        self.mask |= NAV5_MASK_TIME_MASK;
+        self
+    }
+
+    /// Static hold speed threshold (cm/s) and distance threshold (m).
+    /// A zero speed threshold disables static hold.
+    pub fn with_static_hold(mut self, thresh: u8, max_dist: u16) -> Self {
+        self.static_hold_thresh = thresh;
+        self.static_hold_max_dist = max_dist;
+        self.mask |= NAV5_MASK_STATIC_HOLD_MASK;
+        self
+    }
+
+    /// Whether the settings selected by our mask match those in `current`,
+    /// eg as polled from the device after applying them
+    pub fn is_applied_in(&self, current: &CfgNav5) -> bool {
+        let unchanged = |bit: u16, same: bool| self.mask & bit == 0 || same;
+        unchanged(NAV5_MASK_DYN, self.dyn_model == current.dyn_model)
+            && unchanged(NAV5_MASK_MIN_EL, self.min_elev == current.min_elev)
+            && unchanged(
+                NAV5_MASK_POS_FIX_MODE,
+                self.fix_mode == current.fix_mode
+                    && self.fixed_alt == current.fixed_alt
+                    && self.fixed_alt_var == current.fixed_alt_var,
+            )
+            && unchanged(
+                NAV5_MASK_POS_MASK,
+                self.p_dop == current.p_dop && self.p_acc == current.p_acc,
+            )
+            && unchanged(
+                NAV5_MASK_TIME_MASK,
+                self.t_dop == current.t_dop && self.t_acc == current.t_acc,
+            )
+            && unchanged(
+                NAV5_MASK_STATIC_HOLD_MASK,
+                self.static_hold_thresh == current.static_hold_thresh
+                    && self.static_hold_max_dist
+                        == current.static_hold_max_dist,
+            )
+            && unchanged(
+                NAV5_MASK_DGPS_MASK,
+                self.dgnss_timeout == current.dgnss_timeout,
+            )
+            && unchanged(
+                NAV5_MASK_CNO_THRESHOLD,
+                self.cno_thresh_num_svs == current.cno_thresh_num_svs
+                    && self.cno_thresh == current.cno_thresh,
+            )
+            && unchanged(
+                NAV5_MASK_UTC,
+                self.utc_standard == current.utc_standard,
+            )
+    }
+}
+
+pub const UBX_MSG_LEN_CFG_NAV5: usize = 36;
+pub fn cfg_nav5_from_bytes(buf: &[u8]) -> Option<CfgNav5> {
+    if buf.len() < UBX_MSG_LEN_CFG_NAV5 {
+        return None;
+    }
+    Some(CfgNav5 {
+        mask: read_u16(buf, 0),
+        dyn_model: DynamicModel::from_u8(buf[2]),
+        fix_mode: FixMode::from_u8(buf[3])?,
+        fixed_alt: read_i32(buf, 4),
+        fixed_alt_var: read_u32(buf, 8),
+        min_elev: buf[12] as i8,
+        dr_limit: buf[13],
+        p_dop: read_u16(buf, 14),
+        t_dop: read_u16(buf, 16),
+        p_acc: read_u16(buf, 18),
+        t_acc: read_u16(buf, 20),
+        static_hold_thresh: buf[22],
+        dgnss_timeout: buf[23],
+        cno_thresh_num_svs: buf[24],
+        cno_thresh: buf[25],
+        static_hold_max_dist: read_u16(buf, 28),
+        utc_standard: buf[30],
+    })
+}
+
+impl UbxMessage for CfgNav5 {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_NAV5;
+
+    fn from_payload(buf: &[u8]) -> Option<Self> {
+        cfg_nav5_from_bytes(buf)
+    }
+}
+
+impl UbxCommand for CfgNav5 {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_NAV5;
+
+    fn write_payload(&self, buf: &mut [u8]) -> Option<usize> {
+        if buf.len() < UBX_MSG_LEN_CFG_NAV5 {
+            return None;
+        }
+        buf[..UBX_MSG_LEN_CFG_NAV5].fill(0);
+        write_u16(buf, 0, self.mask);
+        buf[2] = self.dyn_model.to_u8();
+        buf[3] = self.fix_mode as u8;
+        write_u32(buf, 4, self.fixed_alt as u32);
+        write_u32(buf, 8, self.fixed_alt_var);
+        buf[12] = self.min_elev as u8;
+        buf[13] = self.dr_limit;
+        write_u16(buf, 14, self.p_dop);
+        write_u16(buf, 16, self.t_dop);
+        write_u16(buf, 18, self.p_acc);
+        write_u16(buf, 20, self.t_acc);
+        buf[22] = self.static_hold_thresh;
+        buf[23] = self.dgnss_timeout;
+        buf[24] = self.cno_thresh_num_svs;
+        buf[25] = self.cno_thresh;
+        write_u16(buf, 28, self.static_hold_max_dist);
+        buf[30] = self.utc_standard;
+        Some(UBX_MSG_LEN_CFG_NAV5)
+    }
+}
+
+/// GNSS identifiers, as used in UBX-CFG-GNSS and UBX-NAV-SAT
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GnssId {
+    Gps = 0,
+    Sbas = 1,
+    Galileo = 2,
+    BeiDou = 3,
+    Imes = 4,
+    Qzss = 5,
+    Glonass = 6,
+}
+
+impl GnssId {
+    pub fn from_u8(gnss_id: u8) -> Option<Self> {
+        match gnss_id {
+            0 => Some(Self::Gps),
+            1 => Some(Self::Sbas),
+            2 => Some(Self::Galileo),
+            3 => Some(Self::BeiDou),
+            4 => Some(Self::Imes),
+            5 => Some(Self::Qzss),
+            6 => Some(Self::Glonass),
+            _ => None,
+        }
+    }
+}
+
+/// UBX-CFG-GNSS block flags: enable this GNSS
+pub const GNSS_FLAGS_ENABLE: u32 = 0x0000_0001;
+/// UBX-CFG-GNSS block flags: the default signal (eg GPS L1C/A) for
+/// each GNSS, in the sigCfgMask field
+pub const GNSS_FLAGS_SIG_CFG_DEFAULT: u32 = 0x0001_0000;
+/// UBX-CFG-GNSS block flags: mask of the sigCfgMask field
+pub const GNSS_FLAGS_SIG_CFG_MASK: u32 = 0x00FF_0000;
+
+/// Configuration of one GNSS, within UBX-CFG-GNSS
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GnssConfigBlock {
+    /// 0 gnssId - System identifier
+    pub gnss_id: GnssId,
+    /// 1 resTrkCh - Number of reserved (minimum) tracking channels
+    pub res_trk_ch: u8,
+    /// 2 maxTrkCh - Maximum number of tracking channels used
+    pub max_trk_ch: u8,
+    /// 4 flags - Enable and sigCfgMask, see GNSS_FLAGS_*
+    pub flags: u32,
+}
+
+impl GnssConfigBlock {
+    /// Enable or disable a GNSS, using its default signal
+    pub fn new(
+        gnss_id: GnssId,
+        enable: bool,
+        res_trk_ch: u8,
+        max_trk_ch: u8,
+    ) -> Self {
+        let enable_flag = if enable { GNSS_FLAGS_ENABLE } else { 0 };
+        Self {
+            gnss_id,
+            res_trk_ch,
+            max_trk_ch,
+            flags: enable_flag | GNSS_FLAGS_SIG_CFG_DEFAULT,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.flags & GNSS_FLAGS_ENABLE != 0
+    }
+
+    /// Signal configuration mask: which signals of this GNSS are used
+    pub fn sig_cfg_mask(&self) -> u8 {
+        ((self.flags & GNSS_FLAGS_SIG_CFG_MASK) >> 16) as u8
+    }
+}
+
+/// Maximum number of GNSS configuration blocks in UBX-CFG-GNSS
+pub const UBX_CFG_GNSS_MAX_BLOCKS: usize = 7;
+
+/// Use all available tracking channels, in UBX-CFG-GNSS numTrkChUse
+pub const GNSS_NUM_TRK_CH_USE_ALL: u8 = 0xFF;
+
+/// UBX-CFG-GNSS message: GNSS system configuration
+/// See 32.10.12 UBX-CFG-GNSS (0x06 0x3E)
+/// Systems not included in the configuration blocks are left unchanged.
+#[derive(Copy, Clone, Debug)]
+pub struct CfgGnss {
+    /// 0 msgVer - Message version (0x00 for this version)
+    pub msg_ver: u8,
+    /// 1 numTrkChHw - Number of tracking channels available in hardware
+    /// (read only)
+    pub num_trk_ch_hw: u8,
+    /// 2 numTrkChUse - Number of tracking channels to use,
+    /// or GNSS_NUM_TRK_CH_USE_ALL
+    pub num_trk_ch_use: u8,
+    /// Number of valid entries in `config_blocks` (numConfigBlocks)
+    pub block_count: usize,
+    /// Configuration blocks, starting at offset 4
+    pub config_blocks: [GnssConfigBlock; UBX_CFG_GNSS_MAX_BLOCKS],
+}
+
+impl CfgGnss {
+    /// A configuration with no GNSS blocks: add some with `with_block`
+    pub fn new(num_trk_ch_use: u8) -> Self {
+        Self {
+            msg_ver: 0,
+            num_trk_ch_hw: 0,
+            num_trk_ch_use,
+            block_count: 0,
+            config_blocks: [GnssConfigBlock::new(GnssId::Gps, false, 0, 0);
+                UBX_CFG_GNSS_MAX_BLOCKS],
+        }
+    }
+
+    /// Add the configuration of a GNSS, replacing any existing
+    /// configuration for that GNSS
+    pub fn with_block(mut self, block: GnssConfigBlock) -> Self {
+        let count = self.block_count;
+        if let Some(existing) = self.config_blocks[..count]
+            .iter_mut()
+            .find(|existing| existing.gnss_id == block.gnss_id)
+        {
+            *existing = block;
+        } else if count < UBX_CFG_GNSS_MAX_BLOCKS {
+            self.config_blocks[count] = block;
+            self.block_count += 1;
+        }
+        self
+    }
+
+    /// The valid configuration blocks
+    pub fn blocks(&self) -> &[GnssConfigBlock] {
+        &self.config_blocks[..self.block_count]
+    }
+
+    /// The configuration block for a GNSS, if any
+    pub fn block(&self, gnss_id: GnssId) -> Option<&GnssConfigBlock> {
+        self.blocks().iter().find(|block| block.gnss_id == gnss_id)
+    }
+
+    /// Check this configuration against the configuration polled from the
+    /// device, which only includes the GNSS the device supports.
+    /// Returns false if we enable a GNSS the device doesn't support,
+    /// or reserve more tracking channels than the device has.
+    pub fn is_supported_by(&self, current: &CfgGnss) -> bool {
+        let hw_channels = current.num_trk_ch_hw as usize;
+        if self.num_trk_ch_use != GNSS_NUM_TRK_CH_USE_ALL
+            && self.num_trk_ch_use > current.num_trk_ch_hw
+        {
+            return false;
+        }
+        let mut reserved_channels = 0;
+        for block in self.blocks() {
+            if current.block(block.gnss_id).is_none() {
+                if block.enabled() {
+                    return false;
+                }
+                continue;
+            }
+            if block.res_trk_ch > block.max_trk_ch {
+                return false;
+            }
+            reserved_channels += block.res_trk_ch as usize;
+        }
+        reserved_channels <= hw_channels
+    }
+}
+
+/// Length of the fixed part of UBX-CFG-GNSS
+pub const UBX_MSG_LEN_CFG_GNSS: usize = 4;
+/// Length of each repeated configuration block in UBX-CFG-GNSS
+pub const UBX_MSG_LEN_CFG_GNSS_BLOCK: usize = 8;
+pub fn cfg_gnss_from_bytes(buf: &[u8]) -> Option<CfgGnss> {
+    if buf.len() < UBX_MSG_LEN_CFG_GNSS {
+        return None;
+    }
+    let num_config_blocks = buf[3] as usize;
+    let blocks_len = buf.len() - UBX_MSG_LEN_CFG_GNSS;
+    if blocks_len < num_config_blocks * UBX_MSG_LEN_CFG_GNSS_BLOCK {
+        return None;
+    }
+    let mut cfg = CfgGnss::new(buf[2]);
+    cfg.msg_ver = buf[0];
+    cfg.num_trk_ch_hw = buf[1];
+    for idx in 0..num_config_blocks {
+        let block =
+            &buf[UBX_MSG_LEN_CFG_GNSS + idx * UBX_MSG_LEN_CFG_GNSS_BLOCK..];
+        // skip systems this driver doesn't know about
+        if let Some(gnss_id) = GnssId::from_u8(block[0]) {
+            cfg = cfg.with_block(GnssConfigBlock {
+                gnss_id,
+                res_trk_ch: block[1],
+                max_trk_ch: block[2],
+                flags: read_u32(block, 4),
+            });
+        }
+    }
+    Some(cfg)
+}
+
+impl UbxMessage for CfgGnss {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_GNSS;
+
+    fn from_payload(buf: &[u8]) -> Option<Self> {
+        cfg_gnss_from_bytes(buf)
+    }
+}
+
+impl UbxCommand for CfgGnss {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_GNSS;
+
+    fn write_payload(&self, buf: &mut [u8]) -> Option<usize> {
+        let pay_len = UBX_MSG_LEN_CFG_GNSS
+            + self.block_count * UBX_MSG_LEN_CFG_GNSS_BLOCK;
+        if buf.len() < pay_len {
+            return None;
+        }
+        buf[0] = self.msg_ver;
+        // numTrkChHw is read only
+        buf[1] = 0;
+        buf[2] = self.num_trk_ch_use;
+        buf[3] = self.block_count as u8;
+        for (idx, block) in self.blocks().iter().enumerate() {
+            let start = UBX_MSG_LEN_CFG_GNSS + idx * UBX_MSG_LEN_CFG_GNSS_BLOCK;
+            buf[start] = block.gnss_id as u8;
+            buf[start + 1] = block.res_trk_ch;
+            buf[start + 2] = block.max_trk_ch;
+            buf[start + 3] = 0;
+            write_u32(buf, start + 4, block.flags);
+        }
+        Some(pay_len)
+    }
+}
+
+/// UBX-CFG-CFG configuration sections: port settings
+pub const CFG_SECTION_IO_PORT: u32 = 0x0000_0001;
+/// UBX-CFG-CFG configuration sections: message configuration
+pub const CFG_SECTION_MSG_CONF: u32 = 0x0000_0002;
+/// UBX-CFG-CFG configuration sections: INF message configuration
+pub const CFG_SECTION_INF_MSG: u32 = 0x0000_0004;
+/// UBX-CFG-CFG configuration sections: navigation configuration
+pub const CFG_SECTION_NAV_CONF: u32 = 0x0000_0008;
+/// UBX-CFG-CFG configuration sections: receiver manager configuration
+pub const CFG_SECTION_RXM_CONF: u32 = 0x0000_0010;
+/// UBX-CFG-CFG configuration sections: sensor interface configuration
+pub const CFG_SECTION_SEN_CONF: u32 = 0x0000_0100;
+/// UBX-CFG-CFG configuration sections: remote inventory configuration
+pub const CFG_SECTION_RINV_CONF: u32 = 0x0000_0200;
+/// UBX-CFG-CFG configuration sections: antenna configuration
+pub const CFG_SECTION_ANT_CONF: u32 = 0x0000_0400;
+/// UBX-CFG-CFG configuration sections: logging configuration
+pub const CFG_SECTION_LOG_CONF: u32 = 0x0000_0800;
+/// UBX-CFG-CFG configuration sections: FTS configuration
+pub const CFG_SECTION_FTS_CONF: u32 = 0x0000_1000;
+/// UBX-CFG-CFG configuration sections: all of the above
+pub const CFG_SECTION_ALL: u32 = 0x0000_1F1F;
+
+/// UBX-CFG-CFG devices: battery backed RAM
+pub const CFG_DEVICE_BBR: u8 = 0x01;
+/// UBX-CFG-CFG devices: flash
+pub const CFG_DEVICE_FLASH: u8 = 0x02;
+/// UBX-CFG-CFG devices: I2C EEPROM
+pub const CFG_DEVICE_EEPROM: u8 = 0x04;
+/// UBX-CFG-CFG devices: SPI flash
+pub const CFG_DEVICE_SPI_FLASH: u8 = 0x10;
+/// UBX-CFG-CFG devices: every non-volatile storage device
+pub const CFG_DEVICE_ALL: u8 = CFG_DEVICE_BBR
+    | CFG_DEVICE_FLASH
+    | CFG_DEVICE_EEPROM
+    | CFG_DEVICE_SPI_FLASH;
+
+/// UBX-CFG-CFG message: clear, save and load configurations
+/// See 32.10.3 UBX-CFG-CFG (0x06 0x09)
+/// The device clears, then saves, then loads the selected sections.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CfgCfg {
+    /// 0 clearMask - Sections to clear in non-volatile storage,
+    /// see CFG_SECTION_*
+    pub clear_mask: u32,
+    /// 4 saveMask - Sections to save from the current configuration to
+    /// non-volatile storage
+    pub save_mask: u32,
+    /// 8 loadMask - Sections to load from non-volatile storage to the
+    /// current configuration
+    pub load_mask: u32,
+    /// 12 deviceMask - Non-volatile storage to use, see CFG_DEVICE_*
+    pub device_mask: u8,
+}
+
+impl CfgCfg {
+    /// Save the current configuration `sections` to `devices`
+    pub fn save(sections: u32, devices: u8) -> Self {
+        Self {
+            clear_mask: 0,
+            save_mask: sections,
+            load_mask: 0,
+            device_mask: devices,
+        }
+    }
+
+    /// Load `sections` from `devices` into the current configuration
+    pub fn load(sections: u32, devices: u8) -> Self {
+        Self {
+            clear_mask: 0,
+            save_mask: 0,
+            load_mask: sections,
+            device_mask: devices,
+        }
+    }
+
+    /// Clear `sections` from `devices`, then load them: this restores
+    /// the default configuration of those sections
+    pub fn reset_to_default(sections: u32, devices: u8) -> Self {
+        Self {
+            clear_mask: sections,
+            save_mask: 0,
+            load_mask: sections,
+            device_mask: devices,
+        }
+    }
+}
+
+pub const UBX_MSG_LEN_CFG_CFG: usize = 13;
+
+impl UbxCommand for CfgCfg {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_CFG;
+
+    fn write_payload(&self, buf: &mut [u8]) -> Option<usize> {
+        if buf.len() < UBX_MSG_LEN_CFG_CFG {
+            return None;
+        }
+        write_u32(buf, 0, self.clear_mask);
+        write_u32(buf, 4, self.save_mask);
+        write_u32(buf, 8, self.load_mask);
+        buf[12] = self.device_mask;
+        Some(UBX_MSG_LEN_CFG_CFG)
+    }
+}
+
+/// UBX-CFG-RST navBbrMask: keep all navigation data (hot start)
+pub const NAV_BBR_HOT_START: u16 = 0x0000;
+/// UBX-CFG-RST navBbrMask: clear ephemeris only (warm start)
+pub const NAV_BBR_WARM_START: u16 = 0x0001;
+/// UBX-CFG-RST navBbrMask: clear all navigation data (cold start)
+pub const NAV_BBR_COLD_START: u16 = 0xFFFF;
+
+/// UBX-CFG-RST resetMode: how the device is reset
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ResetMode {
+    /// Hardware reset (watchdog), immediately
+    HardwareImmediate = 0x00,
+    /// Controlled software reset
+    ControlledSoftware = 0x01,
+    /// Controlled software reset, GNSS only
+    ControlledSoftwareGnssOnly = 0x02,
+    /// Hardware reset (watchdog), after shutdown
+    HardwareAfterShutdown = 0x04,
+    /// Controlled GNSS stop
+    ControlledGnssStop = 0x08,
+    /// Controlled GNSS start
+    ControlledGnssStart = 0x09,
+}
+
+/// UBX-CFG-RST message: reset the receiver, or clear navigation data
+/// See 32.10.26 UBX-CFG-RST (0x06 0x04)
+/// The device doesn't acknowledge this message.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CfgRst {
+    /// 0 navBbrMask - Navigation data to clear, eg NAV_BBR_COLD_START
+    pub nav_bbr_mask: u16,
+    /// 2 resetMode - Type of reset
+    pub reset_mode: ResetMode,
+}
+
+impl CfgRst {
+    /// Restart keeping all navigation data
+    pub fn hot_start(reset_mode: ResetMode) -> Self {
+        Self {
+            nav_bbr_mask: NAV_BBR_HOT_START,
+            reset_mode,
+        }
+    }
+
+    /// Restart without ephemeris data
+    pub fn warm_start(reset_mode: ResetMode) -> Self {
+        Self {
+            nav_bbr_mask: NAV_BBR_WARM_START,
+            reset_mode,
+        }
+    }
+
+    /// Restart without any navigation data
+    pub fn cold_start(reset_mode: ResetMode) -> Self {
+        Self {
+            nav_bbr_mask: NAV_BBR_COLD_START,
+            reset_mode,
+        }
+    }
+}
+
+pub const UBX_MSG_LEN_CFG_RST: usize = 4;
+
+impl UbxCommand for CfgRst {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_RST;
+
+    fn write_payload(&self, buf: &mut [u8]) -> Option<usize> {
+        if buf.len() < UBX_MSG_LEN_CFG_RST {
+            return None;
+        }
+        write_u16(buf, 0, self.nav_bbr_mask);
+        buf[2] = self.reset_mode as u8;
+        buf[3] = 0;
+        Some(UBX_MSG_LEN_CFG_RST)
+    }
+}
+
+/// UBX-CFG-RXM lpMode: receiver power mode
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LowPowerMode {
+    /// Continuous mode: full power
+    Continuous = 0,
+    /// Power save mode, as configured by UBX-CFG-PM2
+    PowerSave = 1,
+}
+
+impl LowPowerMode {
+    pub fn from_u8(lp_mode: u8) -> Option<Self> {
+        match lp_mode {
+            0 => Some(Self::Continuous),
+            1 => Some(Self::PowerSave),
+            _ => None,
+        }
+    }
+}
+
+/// UBX-CFG-RXM message: select continuous or power save mode
+/// See 32.10.28 UBX-CFG-RXM (0x06 0x11)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CfgRxm {
+    /// 1 lpMode - Low power mode
+    pub lp_mode: LowPowerMode,
+}
+
+pub const UBX_MSG_LEN_CFG_RXM: usize = 2;
+pub fn cfg_rxm_from_bytes(buf: &[u8]) -> Option<CfgRxm> {
+    if buf.len() < UBX_MSG_LEN_CFG_RXM {
+        return None;
+    }
+    Some(CfgRxm {
+        lp_mode: LowPowerMode::from_u8(buf[1])?,
+    })
+}
+
+impl UbxMessage for CfgRxm {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_RXM;
+
+    fn from_payload(buf: &[u8]) -> Option<Self> {
+        cfg_rxm_from_bytes(buf)
+    }
+}
+
+impl UbxCommand for CfgRxm {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_RXM;
+
+    fn write_payload(&self, buf: &mut [u8]) -> Option<usize> {
+        if buf.len() < UBX_MSG_LEN_CFG_RXM {
+            return None;
+        }
+        // reserved1: must be set to 8
+        buf[0] = 0x08;
+        buf[1] = self.lp_mode as u8;
+        Some(UBX_MSG_LEN_CFG_RXM)
+    }
+}
+
+/// UBX-CFG-PMS powerSetupValue: power mode presets
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PowerSetup {
+    FullPower = 0x00,
+    Balanced = 0x01,
+    /// Use the `period` and `on_time` of UBX-CFG-PMS
+    Interval = 0x02,
+    Aggressive1Hz = 0x03,
+    Aggressive2Hz = 0x04,
+    Aggressive4Hz = 0x05,
+    /// Reported when the power mode was set by other means, eg UBX-CFG-PM2
+    Invalid = 0xFF,
+}
+
+impl PowerSetup {
+    pub fn from_u8(power_setup: u8) -> Option<Self> {
+        match power_setup {
+            0x00 => Some(Self::FullPower),
+            0x01 => Some(Self::Balanced),
+            0x02 => Some(Self::Interval),
+            0x03 => Some(Self::Aggressive1Hz),
+            0x04 => Some(Self::Aggressive2Hz),
+            0x05 => Some(Self::Aggressive4Hz),
+            0xFF => Some(Self::Invalid),
+            _ => None,
+        }
+    }
+}
+
+/// UBX-CFG-PMS message: select a power mode preset
+/// See 32.10.22 UBX-CFG-PMS (0x06 0x86)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CfgPms {
+    /// 1 powerSetupValue - Power mode preset
+    pub power_setup: PowerSetup,
+    /// 2 period - Position update period (s), for `PowerSetup::Interval`
+    pub period_s: u16,
+    /// 4 onTime - Time to stay in tracking state (s),
+    /// for `PowerSetup::Interval`
+    pub on_time_s: u16,
+}
+
+impl CfgPms {
+    pub fn new(power_setup: PowerSetup) -> Self {
+        Self {
+            power_setup,
+            period_s: 0,
+            on_time_s: 0,
+        }
+    }
+
+    /// Wake up every `period_s`, and track for `on_time_s`
+    pub fn interval(period_s: u16, on_time_s: u16) -> Self {
+        Self {
+            power_setup: PowerSetup::Interval,
+            period_s,
+            on_time_s,
+        }
+    }
+}
+
+pub const UBX_MSG_LEN_CFG_PMS: usize = 8;
+pub fn cfg_pms_from_bytes(buf: &[u8]) -> Option<CfgPms> {
+    if buf.len() < UBX_MSG_LEN_CFG_PMS {
+        return None;
+    }
+    Some(CfgPms {
+        power_setup: PowerSetup::from_u8(buf[1])?,
+        period_s: read_u16(buf, 2),
+        on_time_s: read_u16(buf, 4),
+    })
+}
+
+impl UbxMessage for CfgPms {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_PMS;
+
+    fn from_payload(buf: &[u8]) -> Option<Self> {
+        cfg_pms_from_bytes(buf)
+    }
+}
+
+impl UbxCommand for CfgPms {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_PMS;
+
+    fn write_payload(&self, buf: &mut [u8]) -> Option<usize> {
+        if buf.len() < UBX_MSG_LEN_CFG_PMS {
+            return None;
+        }
+        buf[..UBX_MSG_LEN_CFG_PMS].fill(0);
+        // version 0
+        buf[1] = self.power_setup as u8;
+        write_u16(buf, 2, self.period_s);
+        write_u16(buf, 4, self.on_time_s);
+        Some(UBX_MSG_LEN_CFG_PMS)
+    }
+}
+
+/// UBX-CFG-PM2 flags: use EXTINT1 (rather than EXTINT0) for pin control
+pub const PM2_FLAGS_EXTINT_SEL: u32 = 0x0000_0010;
+/// UBX-CFG-PM2 flags: keep the receiver awake while EXTINT is high
+pub const PM2_FLAGS_EXTINT_WAKE: u32 = 0x0000_0020;
+/// UBX-CFG-PM2 flags: force backup mode while EXTINT is low
+pub const PM2_FLAGS_EXTINT_BACKUP: u32 = 0x0000_0040;
+/// UBX-CFG-PM2 flags: wait for a time fix before entering the off state
+pub const PM2_FLAGS_WAIT_TIME_FIX: u32 = 0x0000_0400;
+/// UBX-CFG-PM2 flags: update the RTC while tracking
+pub const PM2_FLAGS_UPDATE_RTC: u32 = 0x0000_0800;
+/// UBX-CFG-PM2 flags: update ephemeris while tracking
+pub const PM2_FLAGS_UPDATE_EPH: u32 = 0x0000_1000;
+/// UBX-CFG-PM2 flags: stay in acquisition rather than entering the off
+/// state when no fix is found
+pub const PM2_FLAGS_DO_NOT_ENTER_OFF: u32 = 0x0001_0000;
+/// UBX-CFG-PM2 flags: mask of the mode field, see `Pm2Mode`
+pub const PM2_FLAGS_MODE_MASK: u32 = 0x0006_0000;
+const PM2_FLAGS_MODE_SHIFT: u32 = 17;
+
+/// UBX-CFG-PM2 operation mode
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Pm2Mode {
+    /// Power the receiver off between fixes
+    OnOff = 0,
+    /// Stay in tracking state, with reduced power between fixes
+    CyclicTracking = 1,
+}
+
+/// UBX-CFG-PM2 message: power save mode settings, used when UBX-CFG-RXM
+/// selects power save mode
+/// See 32.10.21 UBX-CFG-PM2 (0x06 0x3B), version 1
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CfgPm2 {
+    /// 2 maxStartupStateDur - Maximum time to spend in acquisition
+    /// state (s), or 0 for no limit
+    pub max_startup_state_dur_s: u8,
+    /// 4 flags - see PM2_FLAGS_*
+    pub flags: u32,
+    /// 8 updatePeriod - Position update period (ms),
+    /// or 0 for no updates
+    pub update_period_ms: u32,
+    /// 12 searchPeriod - Acquisition retry period if no fix was found (ms),
+    /// or 0 for no retries
+    pub search_period_ms: u32,
+    /// 16 gridOffset - Offset of the update grid relative to GPS start
+    /// of week (ms)
+    pub grid_offset_ms: u32,
+    /// 20 onTime - Time to stay in tracking state (s)
+    pub on_time_s: u16,
+    /// 22 minAcqTime - Minimum time to spend in acquisition state (s)
+    pub min_acq_time_s: u16,
+}
+
+impl CfgPm2 {
+    /// Cyclic tracking, with a fix every `update_period_ms`
+    pub fn cyclic_tracking(update_period_ms: u32) -> Self {
+        Self::new(Pm2Mode::CyclicTracking, update_period_ms, update_period_ms)
+    }
+
+    /// ON/OFF operation, with a fix every `update_period_ms`, retrying
+    /// acquisition every `search_period_ms` if no fix is found
+    pub fn on_off(update_period_ms: u32, search_period_ms: u32) -> Self {
+        Self::new(Pm2Mode::OnOff, update_period_ms, search_period_ms)
+    }
+
+    fn new(
+        mode: Pm2Mode,
+        update_period_ms: u32,
+        search_period_ms: u32,
+    ) -> Self {
+        Self {
+            max_startup_state_dur_s: 0,
+            flags: PM2_FLAGS_UPDATE_RTC
+                | PM2_FLAGS_UPDATE_EPH
+                | (mode as u32) << PM2_FLAGS_MODE_SHIFT,
+            update_period_ms,
+            search_period_ms,
+            grid_offset_ms: 0,
+            on_time_s: 0,
+            min_acq_time_s: 0,
+        }
+    }
+
+    pub fn with_grid_offset(mut self, grid_offset_ms: u32) -> Self {
+        self.grid_offset_ms = grid_offset_ms;
+        self
+    }
+
+    pub fn with_on_time(mut self, on_time_s: u16) -> Self {
+        self.on_time_s = on_time_s;
+        self
+    }
+
+    pub fn with_flags(mut self, flags: u32) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    pub fn mode(&self) -> Option<Pm2Mode> {
+        match (self.flags & PM2_FLAGS_MODE_MASK) >> PM2_FLAGS_MODE_SHIFT {
+            0 => Some(Pm2Mode::OnOff),
+            1 => Some(Pm2Mode::CyclicTracking),
+            _ => None,
+        }
+    }
+}
+
+pub const UBX_MSG_LEN_CFG_PM2: usize = 44;
+pub fn cfg_pm2_from_bytes(buf: &[u8]) -> Option<CfgPm2> {
+    if buf.len() < UBX_MSG_LEN_CFG_PM2 {
+        return None;
+    }
+    Some(CfgPm2 {
+        max_startup_state_dur_s: buf[2],
+        flags: read_u32(buf, 4),
+        update_period_ms: read_u32(buf, 8),
+        search_period_ms: read_u32(buf, 12),
+        grid_offset_ms: read_u32(buf, 16),
+        on_time_s: read_u16(buf, 20),
+        min_acq_time_s: read_u16(buf, 22),
+    })
+}
+
+impl UbxMessage for CfgPm2 {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_PM2;
+
+    fn from_payload(buf: &[u8]) -> Option<Self> {
+        cfg_pm2_from_bytes(buf)
+    }
+}
+
+impl UbxCommand for CfgPm2 {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_PM2;
+
+    fn write_payload(&self, buf: &mut [u8]) -> Option<usize> {
+        if buf.len() < UBX_MSG_LEN_CFG_PM2 {
+            return None;
+        }
+        buf[..UBX_MSG_LEN_CFG_PM2].fill(0);
+        // version 1
+        buf[0] = 0x01;
+        buf[2] = self.max_startup_state_dur_s;
+        write_u32(buf, 4, self.flags);
+        write_u32(buf, 8, self.update_period_ms);
+        write_u32(buf, 12, self.search_period_ms);
+        write_u32(buf, 16, self.grid_offset_ms);
+        write_u16(buf, 20, self.on_time_s);
+        write_u16(buf, 22, self.min_acq_time_s);
+        Some(UBX_MSG_LEN_CFG_PM2)
+    }
+}
+
+/// Time pulse outputs of the device
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TimePulseId {
+    TimePulse = 0,
+    TimePulse2 = 1,
+}
+
+impl TimePulseId {
+    pub fn from_u8(tp_idx: u8) -> Option<Self> {
+        match tp_idx {
+            0 => Some(Self::TimePulse),
+            1 => Some(Self::TimePulse2),
+            _ => None,
+        }
+    }
+}
+
+/// UBX-CFG-TP5 flags: enable the time pulse
+pub const TP5_FLAGS_ACTIVE: u32 = 0x0000_0001;
+/// UBX-CFG-TP5 flags: synchronize the time pulse to GNSS time when locked
+pub const TP5_FLAGS_LOCK_GNSS_FREQ: u32 = 0x0000_0002;
+/// UBX-CFG-TP5 flags: use the locked period and length when locked
+pub const TP5_FLAGS_LOCKED_OTHER_SET: u32 = 0x0000_0004;
+/// UBX-CFG-TP5 flags: periods are frequencies (Hz) rather than periods (us)
+pub const TP5_FLAGS_IS_FREQ: u32 = 0x0000_0008;
+/// UBX-CFG-TP5 flags: pulse lengths are lengths (us) rather than
+/// duty cycle ratios (2^-32)
+pub const TP5_FLAGS_IS_LENGTH: u32 = 0x0000_0010;
+/// UBX-CFG-TP5 flags: align the pulse to the top of second
+pub const TP5_FLAGS_ALIGN_TO_TOW: u32 = 0x0000_0020;
+/// UBX-CFG-TP5 flags: rising edge at the top of second
+/// (falling edge when clear)
+pub const TP5_FLAGS_POLARITY: u32 = 0x0000_0040;
+/// UBX-CFG-TP5 flags: mask of the gridUtcGnss field, see `TimeRef`
+pub const TP5_FLAGS_GRID_UTC_GNSS_MASK: u32 = 0x0000_0780;
+const TP5_FLAGS_GRID_UTC_GNSS_SHIFT: u32 = 7;
+
+/// UBX-CFG-TP5 message: time pulse parameters
+/// See 32.10.33 UBX-CFG-TP5 (0x06 0x31), version 1
+/// Periods and pulse lengths are used while locked to GNSS time
+/// (the `_lock` fields, if TP5_FLAGS_LOCKED_OTHER_SET is set) or
+/// otherwise, and their units depend on TP5_FLAGS_IS_FREQ and
+/// TP5_FLAGS_IS_LENGTH.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CfgTp5 {
+    /// 0 tpIdx - Time pulse selection
+    pub tp_idx: TimePulseId,
+    /// 4 antCableDelay - Antenna cable delay (ns)
+    pub ant_cable_delay_ns: i16,
+    /// 6 rfGroupDelay - RF group delay (ns), read only
+    pub rf_group_delay_ns: i16,
+    /// 8 freqPeriod - Frequency (Hz) or period (us) when not locked
+    pub freq_period: u32,
+    /// 12 freqPeriodLock - Frequency (Hz) or period (us) when locked
+    pub freq_period_lock: u32,
+    /// 16 pulseLenRatio - Pulse length (us) or duty cycle (2^-32)
+    /// when not locked
+    pub pulse_len_ratio: u32,
+    /// 20 pulseLenRatioLock - Pulse length (us) or duty cycle (2^-32)
+    /// when locked
+    pub pulse_len_ratio_lock: u32,
+    /// 24 userConfigDelay - User configurable time pulse delay (ns)
+    pub user_config_delay_ns: i32,
+    /// 28 flags - see TP5_FLAGS_*
+    pub flags: u32,
+}
+
+impl CfgTp5 {
+    /// The factory default configuration: a 100 ms pulse every second,
+    /// on the rising edge, once locked to GPS time
+    pub fn new(tp_idx: TimePulseId) -> Self {
+        Self {
+            tp_idx,
+            ant_cable_delay_ns: 50,
+            rf_group_delay_ns: 0,
+            freq_period: 1_000_000,
+            freq_period_lock: 1_000_000,
+            pulse_len_ratio: 0,
+            pulse_len_ratio_lock: 100_000,
+            user_config_delay_ns: 0,
+            flags: TP5_FLAGS_ACTIVE
+                | TP5_FLAGS_LOCK_GNSS_FREQ
+                | TP5_FLAGS_LOCKED_OTHER_SET
+                | TP5_FLAGS_IS_LENGTH
+                | TP5_FLAGS_ALIGN_TO_TOW
+                | TP5_FLAGS_POLARITY,
+        }
+    }
+
+    /// Pulse periods (us), when not locked and when locked
+    pub fn with_period_us(
+        mut self,
+        period_us: u32,
+        period_lock_us: u32,
+    ) -> Self {
+        self.flags &= !TP5_FLAGS_IS_FREQ;
+        self.freq_period = period_us;
+        self.freq_period_lock = period_lock_us;
+        self
+    }
+
+    /// Pulse frequencies (Hz), when not locked and when locked
+    pub fn with_frequency_hz(
+        mut self,
+        freq_hz: u32,
+        freq_lock_hz: u32,
+    ) -> Self {
+        self.flags |= TP5_FLAGS_IS_FREQ;
+        self.freq_period = freq_hz;
+        self.freq_period_lock = freq_lock_hz;
+        self
+    }
+
+    /// Pulse lengths (us), when not locked and when locked
+    pub fn with_pulse_len_us(mut self, len_us: u32, len_lock_us: u32) -> Self {
+        self.flags |= TP5_FLAGS_IS_LENGTH;
+        self.pulse_len_ratio = len_us;
+        self.pulse_len_ratio_lock = len_lock_us;
+        self
+    }
+
+    /// Pulse duty cycles (2^-32), when not locked and when locked
+    pub fn with_duty_cycle(mut self, ratio: u32, ratio_lock: u32) -> Self {
+        self.flags &= !TP5_FLAGS_IS_LENGTH;
+        self.pulse_len_ratio = ratio;
+        self.pulse_len_ratio_lock = ratio_lock;
+        self
+    }
+
+    /// Delays to compensate for (ns): antenna cable, and user configured
+    pub fn with_delays_ns(
+        mut self,
+        ant_cable_delay_ns: i16,
+        user_delay_ns: i32,
+    ) -> Self {
+        self.ant_cable_delay_ns = ant_cable_delay_ns;
+        self.user_config_delay_ns = user_delay_ns;
+        self
+    }
+
+    /// Pulse edge at the top of second: rising (true) or falling (false)
+    pub fn with_rising_edge(mut self, rising: bool) -> Self {
+        if rising {
+            self.flags |= TP5_FLAGS_POLARITY;
+        } else {
+            self.flags &= !TP5_FLAGS_POLARITY;
+        }
+        self
+    }
+
+    /// Align the pulse to the top of second of the given time system
+    pub fn with_time_grid(mut self, time_ref: TimeRef) -> Self {
+        self.flags = (self.flags & !TP5_FLAGS_GRID_UTC_GNSS_MASK)
+            | TP5_FLAGS_ALIGN_TO_TOW
+            | (time_ref.to_u16() as u32) << TP5_FLAGS_GRID_UTC_GNSS_SHIFT;
+        self
+    }
+
+    pub fn with_active(mut self, active: bool) -> Self {
+        if active {
+            self.flags |= TP5_FLAGS_ACTIVE;
+        } else {
+            self.flags &= !TP5_FLAGS_ACTIVE;
+        }
+        self
+    }
+
+    pub fn active(&self) -> bool {
+        self.flags & TP5_FLAGS_ACTIVE != 0
+    }
+
+    pub fn is_freq(&self) -> bool {
+        self.flags & TP5_FLAGS_IS_FREQ != 0
+    }
+
+    pub fn is_length(&self) -> bool {
+        self.flags & TP5_FLAGS_IS_LENGTH != 0
+    }
+
+    pub fn rising_edge(&self) -> bool {
+        self.flags & TP5_FLAGS_POLARITY != 0
+    }
+
+    /// Whether these settings match the `current` configuration polled
+    /// from the device, ignoring the read only RF group delay
+    pub fn is_applied_in(&self, current: &CfgTp5) -> bool {
+        current.tp_idx == self.tp_idx
+            && current.flags == self.flags
+            && current.freq_period == self.freq_period
+            && current.freq_period_lock == self.freq_period_lock
+            && current.pulse_len_ratio == self.pulse_len_ratio
+            && current.pulse_len_ratio_lock == self.pulse_len_ratio_lock
+            && current.ant_cable_delay_ns == self.ant_cable_delay_ns
+            && current.user_config_delay_ns == self.user_config_delay_ns
+    }
+
+    /// The time system the pulse is aligned to
+    pub fn time_grid(&self) -> TimeRef {
+        let grid = (self.flags & TP5_FLAGS_GRID_UTC_GNSS_MASK)
+            >> TP5_FLAGS_GRID_UTC_GNSS_SHIFT;
+        TimeRef::from_u16(grid as u16)
+    }
+}
+
+pub const UBX_MSG_LEN_CFG_TP5: usize = 32;
+pub fn cfg_tp5_from_bytes(buf: &[u8]) -> Option<CfgTp5> {
+    if buf.len() < UBX_MSG_LEN_CFG_TP5 {
+        return None;
+    }
+    Some(CfgTp5 {
+        tp_idx: TimePulseId::from_u8(buf[0])?,
+        ant_cable_delay_ns: read_i16(buf, 4),
+        rf_group_delay_ns: read_i16(buf, 6),
+        freq_period: read_u32(buf, 8),
+        freq_period_lock: read_u32(buf, 12),
+        pulse_len_ratio: read_u32(buf, 16),
+        pulse_len_ratio_lock: read_u32(buf, 20),
+        user_config_delay_ns: read_i32(buf, 24),
+        flags: read_u32(buf, 28),
+    })
+}
+
+impl UbxMessage for CfgTp5 {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_TP5;
+
+    fn from_payload(buf: &[u8]) -> Option<Self> {
+        cfg_tp5_from_bytes(buf)
+    }
+}
+
+impl UbxCommand for CfgTp5 {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_TP5;
+
+    fn write_payload(&self, buf: &mut [u8]) -> Option<usize> {
+        if buf.len() < UBX_MSG_LEN_CFG_TP5 {
+            return None;
+        }
+        buf[..UBX_MSG_LEN_CFG_TP5].fill(0);
+        buf[0] = self.tp_idx as u8;
+        // version 1
+        buf[1] = 0x01;
+        write_u16(buf, 4, self.ant_cable_delay_ns as u16);
+        write_u16(buf, 6, self.rf_group_delay_ns as u16);
+        write_u32(buf, 8, self.freq_period);
+        write_u32(buf, 12, self.freq_period_lock);
+        write_u32(buf, 16, self.pulse_len_ratio);
+        write_u32(buf, 20, self.pulse_len_ratio_lock);
+        write_u32(buf, 24, self.user_config_delay_ns as u32);
+        write_u32(buf, 28, self.flags);
+        Some(UBX_MSG_LEN_CFG_TP5)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cfg_msg_sets_rate_per_target() {
+        let cmd = CfgMsg::disabled(UBX_MSG_ID_NAV_PVT)
+            .with_rate(PortId::Uart1, 1)
+            .with_rate(PortId::Usb, 5);
+        let mut buf = [0u8; UBX_MSG_LEN_CFG_MSG];
+        assert_eq!(cmd.write_payload(&mut buf), Some(UBX_MSG_LEN_CFG_MSG));
+        assert_eq!(buf, [0x01, 0x07, 0x00, 0x01, 0x00, 0x05, 0x00, 0x00]);
+        assert_eq!(cmd.write_payload(&mut buf[..7]), None);
+    }
+
+    #[test]
+    fn cfg_msg_current_port_frame() {
+        let cmd = CfgMsgCurrentPort {
+            msg_unique_id: UBX_MSG_ID_NAV_PVT,
+            rate: 1,
+        };
+        let mut payload = [0u8; UBX_MSG_LEN_CFG_MSG_CURRENT_PORT];
+        let pay_len = cmd.write_payload(&mut payload).unwrap();
+        let mut frame = [0u8; 16];
+        let frame_len =
+            encode_ubx_frame(0x06, 0x01, &payload[..pay_len], &mut frame);
+        assert_eq!(frame_len, Some(11));
+        assert_eq!(
+            frame[..11],
+            [
+                0xB5, 0x62, 0x06, 0x01, 0x03, 0x00, 0x01, 0x07, 0x01, 0x13,
+                0x51
+            ]
+        );
+    }
+
+    #[test]
+    fn cfg_prt_uart_round_trip() {
+        // UART1, 8N1 at 115200, UBX+NMEA in, UBX out
+        let bytes = [
+            0x01, 0x00, 0x00, 0x00, 0xD0, 0x08, 0x00, 0x00, 0x00, 0xC2, 0x01,
+            0x00, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let port_cfg = cfg_prt_from_bytes(&bytes).unwrap();
+        assert_eq!(port_cfg.port_id, PortId::Uart1);
+        assert_eq!(port_cfg.baud_rate, 115_200);
+        assert_eq!(port_cfg.in_proto_mask, PROTO_MASK_UBX | PROTO_MASK_NMEA);
+        assert_eq!(port_cfg.out_proto_mask, PROTO_MASK_UBX);
+        assert_eq!(port_cfg.uart_char_len(), 8);
+        assert_eq!(port_cfg.uart_parity(), 0b100);
+        assert_eq!(port_cfg.uart_stop_bits(), 0);
+
+        let mut buf = [0xAAu8; UBX_MSG_LEN_CFG_PRT];
+        assert_eq!(port_cfg.write_payload(&mut buf), Some(UBX_MSG_LEN_CFG_PRT));
+        assert_eq!(buf, bytes);
+        let built = CfgPrt::uart(
+            PortId::Uart1,
+            115_200,
+            PROTO_MASK_UBX | PROTO_MASK_NMEA,
+            PROTO_MASK_UBX,
+        );
+        buf.fill(0xAA);
+        built.write_payload(&mut buf).unwrap();
+        assert_eq!(buf, bytes);
+    }
+
+    #[test]
+    fn cfg_prt_spi_and_ddc_modes() {
+        let spi = CfgPrt::spi(1, 50, PROTO_MASK_UBX, PROTO_MASK_UBX);
+        assert_eq!(spi.spi_mode(), 1);
+        assert_eq!(spi.spi_ff_count(), 50);
+        let mut buf = [0u8; UBX_MSG_LEN_CFG_PRT];
+        spi.write_payload(&mut buf).unwrap();
+        assert_eq!(buf[..8], [0x04, 0x00, 0x00, 0x00, 0x02, 0x32, 0x00, 0x00]);
+
+        let ddc = CfgPrt::ddc(0x42, PROTO_MASK_UBX, PROTO_MASK_UBX);
+        ddc.write_payload(&mut buf).unwrap();
+        assert_eq!(buf[..8], [0x00, 0x00, 0x00, 0x00, 0x84, 0x00, 0x00, 0x00]);
+        assert_eq!(cfg_prt_from_bytes(&buf).unwrap().ddc_address(), 0x42);
+        // unknown port
+        buf[0] = 7;
+        assert!(cfg_prt_from_bytes(&buf).is_none());
+        assert!(cfg_prt_from_bytes(&buf[..19]).is_none());
+    }
+
+    #[test]
+    fn cfg_rate_round_trip() {
+        let bytes = [0xC8, 0x00, 0x01, 0x00, 0x01, 0x00];
+        let rate = CfgRate::new(200);
+        let mut buf = [0u8; UBX_MSG_LEN_CFG_RATE];
+        assert_eq!(rate.write_payload(&mut buf), Some(UBX_MSG_LEN_CFG_RATE));
+        assert_eq!(buf, bytes);
+        assert_eq!(cfg_rate_from_bytes(&bytes), Some(rate));
+        assert!(cfg_rate_from_bytes(&bytes[..5]).is_none());
+    }
+
+    #[test]
+    fn cfg_nav5_round_trip() {
+        let mut bytes = [0u8; UBX_MSG_LEN_CFG_NAV5];
+        bytes[..4].copy_from_slice(&[0x43, 0x00, 0x04, 0x03]);
+        bytes[12] = 10;
+        bytes[22] = 20;
+        bytes[28..30].copy_from_slice(&[0xC8, 0x00]);
+        let nav5 = CfgNav5::default()
+            .with_dyn_model(DynamicModel::Automotive)
+            .with_min_elev(10)
+            .with_static_hold(20, 200);
+        let mut buf = [0xAAu8; UBX_MSG_LEN_CFG_NAV5];
+        assert_eq!(nav5.write_payload(&mut buf), Some(UBX_MSG_LEN_CFG_NAV5));
+        assert_eq!(buf, bytes);
+        assert_eq!(cfg_nav5_from_bytes(&bytes), Some(nav5));
+    }
+
+    #[test]
+    fn cfg_nav5_keeps_unknown_dynamic_model() {
+        let mut bytes = [0u8; UBX_MSG_LEN_CFG_NAV5];
+        bytes[..4].copy_from_slice(&[0x05, 0x00, 0x0B, 0x02]);
+        bytes[4..12]
+            .copy_from_slice(&[0x10, 0x27, 0x00, 0x00, 0x10, 0x27, 0x00, 0x00]);
+        let nav5 = cfg_nav5_from_bytes(&bytes).unwrap();
+        assert_eq!(nav5.dyn_model, DynamicModel::Other(11));
+        assert_eq!(nav5.fixed_alt, 10_000);
+
+        let expected = CfgNav5::default()
+            .with_dyn_model(DynamicModel::Other(11))
+            .with_fix_mode(FixMode::Only3D, 10_000, 10_000);
+        assert_eq!(nav5, expected);
+        let mut buf = [0u8; UBX_MSG_LEN_CFG_NAV5];
+        expected.write_payload(&mut buf).unwrap();
+        assert_eq!(buf, bytes);
+    }
+
+    #[test]
+    fn cfg_rate_keeps_unknown_time_ref() {
+        let bytes = [0xC8, 0x00, 0x01, 0x00, 0x05, 0x00];
+        let rate = cfg_rate_from_bytes(&bytes).unwrap();
+        assert_eq!(rate.time_ref, TimeRef::Other(5));
+        let mut buf = [0u8; UBX_MSG_LEN_CFG_RATE];
+        rate.write_payload(&mut buf).unwrap();
+        assert_eq!(buf, bytes);
+    }
+
+    #[test]
+    fn cfg_nav5_applied_ignores_unmasked_settings() {
+        let nav5 = CfgNav5::default().with_dyn_model(DynamicModel::Sea);
+        let mut current = nav5.with_min_elev(5);
+        assert!(nav5.is_applied_in(&current));
+        current.dyn_model = DynamicModel::Portable;
+        assert!(!nav5.is_applied_in(&current));
+    }
+
+    #[test]
+    fn cfg_gnss_round_trip() {
+        // GPS enabled with 8-16 channels, GLONASS disabled
+        let bytes = [
+            0x00, 0x00, 0x20, 0x02, 0x00, 0x08, 0x10, 0x00, 0x01, 0x00, 0x01,
+            0x00, 0x06, 0x00, 0x0E, 0x00, 0x00, 0x00, 0x01, 0x00,
+        ];
+        let gnss = CfgGnss::new(32)
+            .with_block(GnssConfigBlock::new(GnssId::Gps, true, 8, 16))
+            .with_block(GnssConfigBlock::new(GnssId::Glonass, false, 0, 14));
+        let mut buf = [0xAAu8; 32];
+        assert_eq!(gnss.write_payload(&mut buf), Some(bytes.len()));
+        assert_eq!(buf[..bytes.len()], bytes);
+
+        let decoded = cfg_gnss_from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.num_trk_ch_use, 32);
+        assert_eq!(decoded.blocks(), gnss.blocks());
+        assert!(decoded.block(GnssId::Gps).unwrap().enabled());
+        assert_eq!(decoded.block(GnssId::Gps).unwrap().sig_cfg_mask(), 1);
+        assert!(!decoded.block(GnssId::Glonass).unwrap().enabled());
+        assert!(cfg_gnss_from_bytes(&bytes[..19]).is_none());
+    }
+
+    #[test]
+    fn cfg_gnss_support_check() {
+        let mut current = CfgGnss::new(GNSS_NUM_TRK_CH_USE_ALL)
+            .with_block(GnssConfigBlock::new(GnssId::Gps, true, 8, 16))
+            .with_block(GnssConfigBlock::new(GnssId::Glonass, true, 8, 14));
+        current.num_trk_ch_hw = 32;
+        let galileo = CfgGnss::new(GNSS_NUM_TRK_CH_USE_ALL)
+            .with_block(GnssConfigBlock::new(GnssId::Galileo, true, 4, 8));
+        assert!(!galileo.is_supported_by(&current));
+        let gps_only = CfgGnss::new(GNSS_NUM_TRK_CH_USE_ALL)
+            .with_block(GnssConfigBlock::new(GnssId::Gps, true, 8, 32))
+            .with_block(GnssConfigBlock::new(GnssId::Glonass, false, 0, 14));
+        assert!(gps_only.is_supported_by(&current));
+        assert!(!CfgGnss::new(40).is_supported_by(&current));
+    }
+
+    #[test]
+    fn cfg_cfg_save_all_to_bbr_and_flash() {
+        let cmd =
+            CfgCfg::save(CFG_SECTION_ALL, CFG_DEVICE_BBR | CFG_DEVICE_FLASH);
+        let mut buf = [0u8; UBX_MSG_LEN_CFG_CFG];
+        assert_eq!(cmd.write_payload(&mut buf), Some(UBX_MSG_LEN_CFG_CFG));
+        assert_eq!(
+            buf,
+            [
+                0x00, 0x00, 0x00, 0x00, 0x1F, 0x1F, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x03
+            ]
+        );
+        assert_eq!(cmd.write_payload(&mut buf[..12]), None);
+    }
+
+    #[test]
+    fn cfg_cfg_reset_to_default_clears_and_loads() {
+        let cmd =
+            CfgCfg::reset_to_default(CFG_SECTION_NAV_CONF, CFG_DEVICE_ALL);
+        let mut buf = [0u8; UBX_MSG_LEN_CFG_CFG];
+        cmd.write_payload(&mut buf).unwrap();
+        assert_eq!(
+            buf,
+            [
+                0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00,
+                0x00, 0x00, 0x17
+            ]
+        );
+        let load = CfgCfg::load(CFG_SECTION_IO_PORT, CFG_DEVICE_FLASH);
+        load.write_payload(&mut buf).unwrap();
+        assert_eq!(buf[8..], [0x01, 0x00, 0x00, 0x00, 0x02]);
+    }
+
+    #[test]
+    fn cfg_rst_payloads() {
+        let mut buf = [0xAAu8; UBX_MSG_LEN_CFG_RST];
+        let cold = CfgRst::cold_start(ResetMode::HardwareImmediate);
+        assert_eq!(cold.write_payload(&mut buf), Some(UBX_MSG_LEN_CFG_RST));
+        assert_eq!(buf, [0xFF, 0xFF, 0x00, 0x00]);
+        let warm = CfgRst::warm_start(ResetMode::ControlledSoftware);
+        warm.write_payload(&mut buf).unwrap();
+        assert_eq!(buf, [0x01, 0x00, 0x01, 0x00]);
+        let hot = CfgRst::hot_start(ResetMode::ControlledGnssStart);
+        hot.write_payload(&mut buf).unwrap();
+        assert_eq!(buf, [0x00, 0x00, 0x09, 0x00]);
+        assert_eq!(hot.write_payload(&mut buf[..3]), None);
+    }
+
+    #[test]
+    fn cfg_rxm_round_trip() {
+        let rxm = CfgRxm {
+            lp_mode: LowPowerMode::PowerSave,
+        };
+        let mut buf = [0u8; UBX_MSG_LEN_CFG_RXM];
+        assert_eq!(rxm.write_payload(&mut buf), Some(UBX_MSG_LEN_CFG_RXM));
+        assert_eq!(buf, [0x08, 0x01]);
+        assert_eq!(cfg_rxm_from_bytes(&buf), Some(rxm));
+        assert!(cfg_rxm_from_bytes(&[0x08, 0x04]).is_none());
+    }
+
+    #[test]
+    fn cfg_pms_round_trip() {
+        let bytes = [0x00, 0x02, 0x3C, 0x00, 0x0A, 0x00, 0x00, 0x00];
+        let pms = CfgPms::interval(60, 10);
+        let mut buf = [0xAAu8; UBX_MSG_LEN_CFG_PMS];
+        assert_eq!(pms.write_payload(&mut buf), Some(UBX_MSG_LEN_CFG_PMS));
+        assert_eq!(buf, bytes);
+        assert_eq!(cfg_pms_from_bytes(&bytes), Some(pms));
+        let full = cfg_pms_from_bytes(&[0, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+        assert_eq!(full, CfgPms::new(PowerSetup::FullPower));
+    }
+
+    #[test]
+    fn cfg_pm2_round_trip() {
+        let mut bytes = [0u8; UBX_MSG_LEN_CFG_PM2];
+        bytes[0] = 0x01;
+        // updateRTC | updateEPH | cyclic tracking mode
+        bytes[4..8].copy_from_slice(&[0x00, 0x18, 0x02, 0x00]);
+        bytes[8..12].copy_from_slice(&[0xE8, 0x03, 0x00, 0x00]);
+        bytes[12..16].copy_from_slice(&[0xE8, 0x03, 0x00, 0x00]);
+        bytes[20..22].copy_from_slice(&[0x02, 0x00]);
+        let pm2 = CfgPm2::cyclic_tracking(1000).with_on_time(2);
+        let mut buf = [0xAAu8; UBX_MSG_LEN_CFG_PM2];
+        assert_eq!(pm2.write_payload(&mut buf), Some(UBX_MSG_LEN_CFG_PM2));
+        assert_eq!(buf, bytes);
+        let decoded = cfg_pm2_from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, pm2);
+        assert_eq!(decoded.mode(), Some(Pm2Mode::CyclicTracking));
+        assert_eq!(CfgPm2::on_off(10_000, 60_000).mode(), Some(Pm2Mode::OnOff));
+    }
+
+    #[test]
+    fn cfg_tp5_round_trip() {
+        // 10 Hz, 100 ms pulses, aligned to GPS time
+        let bytes = [
+            0x00, 0x01, 0x00, 0x00, 0x32, 0x00, 0x00, 0x00, 0x0A, 0x00, 0x00,
+            0x00, 0x0A, 0x00, 0x00, 0x00, 0xA0, 0x86, 0x01, 0x00, 0xA0, 0x86,
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0x00, 0x00, 0x00,
+        ];
+        let tp5 = CfgTp5::new(TimePulseId::TimePulse)
+            .with_frequency_hz(10, 10)
+            .with_pulse_len_us(100_000, 100_000)
+            .with_time_grid(TimeRef::Gps);
+        let mut buf = [0xAAu8; UBX_MSG_LEN_CFG_TP5];
+        assert_eq!(tp5.write_payload(&mut buf), Some(UBX_MSG_LEN_CFG_TP5));
+        assert_eq!(buf, bytes);
+
+        let decoded = cfg_tp5_from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, tp5);
+        assert!(decoded.active() && decoded.is_freq() && decoded.is_length());
+        assert!(decoded.rising_edge());
+        assert_eq!(decoded.time_grid(), TimeRef::Gps);
+        assert!(tp5.is_applied_in(&decoded));
+        assert!(!tp5.with_active(false).is_applied_in(&decoded));
+        assert!(cfg_tp5_from_bytes(&bytes[..31]).is_none());
+    }
+}
@@ -0,0 +1,581 @@
+/*
+Copyright (c) 2020 Todd Stellanova
+LICENSE: BSD3 (see LICENSE file)
+*/
+
+//! Key-value configuration (UBX-CFG-VALSET, UBX-CFG-VALGET, UBX-CFG-VALDEL),
+//! used by generation 9 and later devices (eg M9, M10, F9) in place of
+//! the legacy UBX-CFG messages.
+
+use core::marker::PhantomData;
+
+use crate::cfg::PortId;
+use crate::messages::*;
+
+/// A value that can be stored in a configuration item
+pub trait CfgValue: Sized + Copy {
+    /// Storage size in bytes
+    const SIZE: usize;
+    /// Write the little-endian value to the start of `buf`
+    fn write_le(self, buf: &mut [u8]);
+    /// Read a little-endian value from the start of `buf`
+    fn read_le(buf: &[u8]) -> Self;
+}
+
+impl CfgValue for bool {
+    const SIZE: usize = 1;
+
+    fn write_le(self, buf: &mut [u8]) {
+        buf[0] = self as u8;
+    }
+
+    fn read_le(buf: &[u8]) -> Self {
+        buf[0] & 0x01 != 0
+    }
+}
+
+macro_rules! impl_cfg_value {
+    ($($value_type:ty),*) => {
+        $(
+            impl CfgValue for $value_type {
+                const SIZE: usize = core::mem::size_of::<$value_type>();
+
+                fn write_le(self, buf: &mut [u8]) {
+                    buf[..Self::SIZE].copy_from_slice(&self.to_le_bytes());
+                }
+
+                fn read_le(buf: &[u8]) -> Self {
+                    let mut bytes = [0u8; Self::SIZE];
+                    bytes.copy_from_slice(&buf[..Self::SIZE]);
+                    <$value_type>::from_le_bytes(bytes)
+                }
+            }
+        )*
+    };
+}
+
+impl_cfg_value!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+
+/// Storage size, in bytes, of the value of a configuration item,
+/// from bits 28..30 of its key ID
+pub fn key_storage_size(key_id: u32) -> Option<usize> {
+    match (key_id >> 28) & 0x07 {
+        0x01 | 0x02 => Some(1),
+        0x03 => Some(2),
+        0x04 => Some(4),
+        0x05 => Some(8),
+        _ => None,
+    }
+}
+
+/// The key of a configuration item, typed by its value
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CfgKey<T> {
+    /// Key ID, eg 0x30210001 for CFG-RATE-MEAS
+    pub id: u32,
+    /// Physical value is stored value * scale (1.0 for unscaled items)
+    pub scale: f32,
+    value_type: PhantomData<T>,
+}
+
+impl<T: CfgValue> CfgKey<T> {
+    /// An unscaled configuration item
+    pub const fn new(id: u32) -> Self {
+        Self::with_scale(id, 1.0)
+    }
+
+    /// A configuration item stored in units of `scale`,
+    /// eg 0.1 for a DOP stored as tenths
+    pub const fn with_scale(id: u32, scale: f32) -> Self {
+        Self {
+            id,
+            scale,
+            value_type: PhantomData,
+        }
+    }
+
+    /// Convert a stored value to its physical value
+    pub fn to_physical(&self, value: T) -> f32
+    where
+        T: Into<f32>,
+    {
+        value.into() * self.scale
+    }
+}
+
+/// Key IDs of commonly used configuration items.
+/// See the configuration reference in the interface description.
+pub mod keys {
+    use super::CfgKey;
+
+    /// CFG-RATE-MEAS - Time between GNSS measurements (ms)
+    pub const RATE_MEAS: CfgKey<u16> = CfgKey::new(0x3021_0001);
+    /// CFG-RATE-NAV - Number of measurements per navigation solution
+    pub const RATE_NAV: CfgKey<u16> = CfgKey::new(0x3021_0002);
+    /// CFG-RATE-TIMEREF - Time system measurements are aligned to,
+    /// see `cfg::TimeRef`
+    pub const RATE_TIMEREF: CfgKey<u8> = CfgKey::new(0x2021_0003);
+
+    /// CFG-NAVSPG-FIXMODE - Position fix mode, see `cfg::FixMode`
+    pub const NAVSPG_FIXMODE: CfgKey<u8> = CfgKey::new(0x2011_0011);
+    /// CFG-NAVSPG-DYNMODEL - Dynamic platform model,
+    /// see `cfg::DynamicModel`
+    pub const NAVSPG_DYNMODEL: CfgKey<u8> = CfgKey::new(0x2011_0021);
+    /// CFG-NAVSPG-INFIL_MINELEV - Minimum elevation for a GNSS satellite
+    /// to be used in navigation (degrees)
+    pub const NAVSPG_INFIL_MINELEV: CfgKey<i8> = CfgKey::new(0x2011_00A4);
+    /// CFG-NAVSPG-OUTFIL_PDOP - Output filter position DOP mask
+    pub const NAVSPG_OUTFIL_PDOP: CfgKey<u16> =
+        CfgKey::with_scale(0x3011_00B1, 0.1);
+    /// CFG-NAVSPG-OUTFIL_TDOP - Output filter time DOP mask
+    pub const NAVSPG_OUTFIL_TDOP: CfgKey<u16> =
+        CfgKey::with_scale(0x3011_00B2, 0.1);
+    /// CFG-NAVSPG-OUTFIL_PACC - Output filter position accuracy mask (m)
+    pub const NAVSPG_OUTFIL_PACC: CfgKey<u16> = CfgKey::new(0x3011_00B3);
+    /// CFG-NAVSPG-OUTFIL_TACC - Output filter time accuracy mask (m)
+    pub const NAVSPG_OUTFIL_TACC: CfgKey<u16> = CfgKey::new(0x3011_00B4);
+
+    /// CFG-SIGNAL-GPS_ENA - GPS enable
+    pub const SIGNAL_GPS_ENA: CfgKey<bool> = CfgKey::new(0x1031_001F);
+    /// CFG-SIGNAL-SBAS_ENA - SBAS enable
+    pub const SIGNAL_SBAS_ENA: CfgKey<bool> = CfgKey::new(0x1031_0020);
+    /// CFG-SIGNAL-GAL_ENA - Galileo enable
+    pub const SIGNAL_GAL_ENA: CfgKey<bool> = CfgKey::new(0x1031_0021);
+    /// CFG-SIGNAL-BDS_ENA - BeiDou enable
+    pub const SIGNAL_BDS_ENA: CfgKey<bool> = CfgKey::new(0x1031_0022);
+    /// CFG-SIGNAL-QZSS_ENA - QZSS enable
+    pub const SIGNAL_QZSS_ENA: CfgKey<bool> = CfgKey::new(0x1031_0024);
+    /// CFG-SIGNAL-GLO_ENA - GLONASS enable
+    pub const SIGNAL_GLO_ENA: CfgKey<bool> = CfgKey::new(0x1031_0025);
+
+    /// CFG-UART1-BAUDRATE - UART1 baud rate
+    pub const UART1_BAUDRATE: CfgKey<u32> = CfgKey::new(0x4052_0001);
+    /// CFG-UART2-BAUDRATE - UART2 baud rate
+    pub const UART2_BAUDRATE: CfgKey<u32> = CfgKey::new(0x4053_0001);
+}
+
+/// CFG-xxxINPROT-UBX key ID of each port, in `PortId` order
+const IN_PROT_UBX_KEY_IDS: [u32; 5] = [
+    0x1071_0001,
+    0x1073_0001,
+    0x1075_0001,
+    0x1077_0001,
+    0x1079_0001,
+];
+/// CFG-xxxOUTPROT-UBX key ID of each port, in `PortId` order
+const OUT_PROT_UBX_KEY_IDS: [u32; 5] = [
+    0x1072_0001,
+    0x1074_0001,
+    0x1076_0001,
+    0x1078_0001,
+    0x107A_0001,
+];
+/// Offset from a CFG-xxxPROT-UBX key ID to the matching -NMEA key ID
+const PROT_NMEA_KEY_OFFSET: u32 = 1;
+
+/// CFG-xxxINPROT-UBX: accept UBX input on `port`
+pub fn in_prot_ubx_key(port: PortId) -> CfgKey<bool> {
+    CfgKey::new(IN_PROT_UBX_KEY_IDS[port as usize])
+}
+
+/// CFG-xxxINPROT-NMEA: accept NMEA input on `port`
+pub fn in_prot_nmea_key(port: PortId) -> CfgKey<bool> {
+    CfgKey::new(IN_PROT_UBX_KEY_IDS[port as usize] + PROT_NMEA_KEY_OFFSET)
+}
+
+/// CFG-xxxOUTPROT-UBX: send UBX output on `port`
+pub fn out_prot_ubx_key(port: PortId) -> CfgKey<bool> {
+    CfgKey::new(OUT_PROT_UBX_KEY_IDS[port as usize])
+}
+
+/// CFG-xxxOUTPROT-NMEA: send NMEA output on `port`
+pub fn out_prot_nmea_key(port: PortId) -> CfgKey<bool> {
+    CfgKey::new(OUT_PROT_UBX_KEY_IDS[port as usize] + PROT_NMEA_KEY_OFFSET)
+}
+
+/// CFG-MSGOUT-xxx_I2C key ID of messages with known output rate keys.
+/// The keys for the other ports follow in `PortId` order.
+const MSG_OUT_KEY_IDS: [(u16, u32); 7] = [
+    (UBX_MSG_ID_NAV_PVT, 0x2091_0006),
+    (UBX_MSG_ID_NAV_STATUS, 0x2091_001A),
+    (UBX_MSG_ID_NAV_SAT, 0x2091_0015),
+    (UBX_MSG_ID_NAV_DOP, 0x2091_0038),
+    (UBX_MSG_ID_MON_HW, 0x2091_01B4),
+    (UBX_MSG_ID_TIM_TP, 0x2091_017D),
+    (UBX_MSG_ID_TIM_TM2, 0x2091_0178),
+];
+
+/// CFG-MSGOUT-xxx: output rate of a message on `port`, relative to the
+/// navigation rate (0 disables it), if the message's key is known
+pub fn msg_out_key(msg_unique_id: u16, port: PortId) -> Option<CfgKey<u8>> {
+    MSG_OUT_KEY_IDS
+        .iter()
+        .find(|(id, _)| *id == msg_unique_id)
+        .map(|(_, base_key_id)| CfgKey::new(base_key_id + port as u32))
+}
+
+/// Configuration layers to set or delete values in (bit mask)
+pub const VAL_LAYER_RAM: u8 = 0x01;
+pub const VAL_LAYER_BBR: u8 = 0x02;
+pub const VAL_LAYER_FLASH: u8 = 0x04;
+
+/// Configuration layer to read values from, with UBX-CFG-VALGET
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ValGetLayer {
+    Ram = 0,
+    Bbr = 1,
+    Flash = 2,
+    /// The default value of each item
+    Default = 7,
+}
+
+impl ValGetLayer {
+    pub fn from_u8(layer: u8) -> Option<Self> {
+        match layer {
+            0 => Some(Self::Ram),
+            1 => Some(Self::Bbr),
+            2 => Some(Self::Flash),
+            7 => Some(Self::Default),
+            _ => None,
+        }
+    }
+}
+
+/// Transaction actions for UBX-CFG-VALSET and UBX-CFG-VALDEL.
+/// Changes made within a transaction are only applied, all at once,
+/// by the message that ends it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ValTransaction {
+    /// Apply this message's changes immediately
+    None = 0,
+    /// Discard any previous transaction and start a new one
+    Begin = 1,
+    /// Add this message's changes to the current transaction
+    Continue = 2,
+    /// Add this message's changes, then apply the whole transaction
+    Apply = 3,
+}
+
+/// Length of the fixed part of UBX-CFG-VALSET, -VALGET and -VALDEL
+pub const UBX_MSG_LEN_CFG_VAL_HEADER: usize = 4;
+/// Maximum length of the key-value data in a UBX-CFG-VALSET we send:
+/// limited by our write buffer, rather than the protocol
+pub const UBX_CFG_VALSET_MAX_DATA_LEN: usize = 112;
+/// Maximum number of keys in a UBX-CFG-VALGET or UBX-CFG-VALDEL we send
+pub const UBX_CFG_VAL_MAX_KEYS: usize = 16;
+/// Default maximum length of the key-value data in a UBX-CFG-VALGET
+/// response: enough for any response that fits in the default read buffer
+pub const UBX_CFG_VALGET_MAX_DATA_LEN: usize =
+    crate::DEFAULT_READ_BUF_LEN - UBX_MSG_LEN_CFG_VAL_HEADER;
+const UBX_CFG_VAL_KEY_LEN: usize = 4;
+
+/// Fixed part of UBX-CFG-VALSET and UBX-CFG-VALDEL
+fn write_val_header(buf: &mut [u8], layers: u8, transaction: ValTransaction) {
+    // version 1 supports transactions
+    buf[0] = if transaction == ValTransaction::None {
+        0
+    } else {
+        1
+    };
+    buf[1] = layers;
+    buf[2] = transaction as u8;
+    buf[3] = 0;
+}
+
+/// UBX-CFG-VALSET message: set the values of configuration items
+/// See UBX-CFG-VALSET (0x06 0x8A) in the interface description
+#[derive(Copy, Clone, Debug)]
+pub struct CfgValSet {
+    /// Layers to set the values in, see VAL_LAYER_*
+    pub layers: u8,
+    pub transaction: ValTransaction,
+    /// Key-value pairs, each a little-endian u32 key ID and value
+    data: [u8; UBX_CFG_VALSET_MAX_DATA_LEN],
+    data_len: usize,
+    /// Set when an item couldn't be added: more items were added than
+    /// fit in one message, or a value doesn't match its key's size
+    invalid: bool,
+}
+
+impl CfgValSet {
+    /// Set values in `layers` (see VAL_LAYER_*), outside of a transaction
+    pub fn new(layers: u8) -> Self {
+        Self {
+            layers,
+            transaction: ValTransaction::None,
+            data: [0; UBX_CFG_VALSET_MAX_DATA_LEN],
+            data_len: 0,
+            invalid: false,
+        }
+    }
+
+    pub fn with_transaction(mut self, transaction: ValTransaction) -> Self {
+        self.transaction = transaction;
+        self
+    }
+
+    /// Add the value of a configuration item.
+    /// If the item doesn't fit, the message can't be sent
+    /// (`Error::MessageTooLong`): split the items across messages instead,
+    /// within a transaction if they must be applied together.
+    /// Likewise if the key's storage size (see `key_storage_size`)
+    /// doesn't match the size of `T`.
+    pub fn with<T: CfgValue>(mut self, key: CfgKey<T>, value: T) -> Self {
+        let item_len = UBX_CFG_VAL_KEY_LEN + T::SIZE;
+        if key_storage_size(key.id) != Some(T::SIZE)
+            || self.data_len + item_len > UBX_CFG_VALSET_MAX_DATA_LEN
+        {
+            self.invalid = true;
+            return self;
+        }
+        write_u32(&mut self.data, self.data_len, key.id);
+        value.write_le(&mut self.data[self.data_len + UBX_CFG_VAL_KEY_LEN..]);
+        self.data_len += item_len;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data_len == 0
+    }
+}
+
+impl UbxCommand for CfgValSet {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_VALSET;
+
+    fn write_payload(&self, buf: &mut [u8]) -> Option<usize> {
+        let pay_len = UBX_MSG_LEN_CFG_VAL_HEADER + self.data_len;
+        if self.invalid || buf.len() < pay_len {
+            return None;
+        }
+        write_val_header(buf, self.layers, self.transaction);
+        buf[UBX_MSG_LEN_CFG_VAL_HEADER..pay_len]
+            .copy_from_slice(&self.data[..self.data_len]);
+        Some(pay_len)
+    }
+}
+
+/// UBX-CFG-VALDEL message: delete configuration items from the
+/// BBR and/or flash layers, so that their default values apply
+/// See UBX-CFG-VALDEL (0x06 0x8C) in the interface description
+#[derive(Copy, Clone, Debug)]
+pub struct CfgValDel {
+    /// Layers to delete the items from: VAL_LAYER_BBR and/or VAL_LAYER_FLASH
+    pub layers: u8,
+    pub transaction: ValTransaction,
+    key_ids: [u32; UBX_CFG_VAL_MAX_KEYS],
+    key_count: usize,
+    /// Set when more keys were added than fit in one message
+    overflow: bool,
+}
+
+impl CfgValDel {
+    pub fn new(layers: u8) -> Self {
+        Self {
+            layers,
+            transaction: ValTransaction::None,
+            key_ids: [0; UBX_CFG_VAL_MAX_KEYS],
+            key_count: 0,
+            overflow: false,
+        }
+    }
+
+    pub fn with_transaction(mut self, transaction: ValTransaction) -> Self {
+        self.transaction = transaction;
+        self
+    }
+
+    /// Add a configuration item to delete
+    pub fn with_key<T>(mut self, key: CfgKey<T>) -> Self {
+        if self.key_count < UBX_CFG_VAL_MAX_KEYS {
+            self.key_ids[self.key_count] = key.id;
+            self.key_count += 1;
+        } else {
+            self.overflow = true;
+        }
+        self
+    }
+}
+
+impl UbxCommand for CfgValDel {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_VALDEL;
+
+    fn write_payload(&self, buf: &mut [u8]) -> Option<usize> {
+        let pay_len =
+            UBX_MSG_LEN_CFG_VAL_HEADER + self.key_count * UBX_CFG_VAL_KEY_LEN;
+        if self.overflow || buf.len() < pay_len {
+            return None;
+        }
+        write_val_header(buf, self.layers, self.transaction);
+        for (idx, key_id) in self.key_ids[..self.key_count].iter().enumerate() {
+            let start = UBX_MSG_LEN_CFG_VAL_HEADER + idx * UBX_CFG_VAL_KEY_LEN;
+            write_u32(buf, start, *key_id);
+        }
+        Some(pay_len)
+    }
+}
+
+/// UBX-CFG-VALGET poll request: read the values of configuration items
+/// See UBX-CFG-VALGET (0x06 0x8B) in the interface description
+#[derive(Copy, Clone, Debug)]
+pub struct CfgValGet {
+    pub layer: ValGetLayer,
+    /// Number of values to skip in the response, for requests
+    /// (eg with wildcard keys) whose response doesn't fit in one message
+    pub position: u16,
+    key_ids: [u32; UBX_CFG_VAL_MAX_KEYS],
+    key_count: usize,
+    /// Set when more keys were added than fit in one message
+    overflow: bool,
+}
+
+impl CfgValGet {
+    pub fn new(layer: ValGetLayer) -> Self {
+        Self {
+            layer,
+            position: 0,
+            key_ids: [0; UBX_CFG_VAL_MAX_KEYS],
+            key_count: 0,
+            overflow: false,
+        }
+    }
+
+    /// Add a configuration item to read
+    pub fn with_key<T>(mut self, key: CfgKey<T>) -> Self {
+        if self.key_count < UBX_CFG_VAL_MAX_KEYS {
+            self.key_ids[self.key_count] = key.id;
+            self.key_count += 1;
+        } else {
+            self.overflow = true;
+        }
+        self
+    }
+}
+
+impl UbxCommand for CfgValGet {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_VALGET;
+
+    fn write_payload(&self, buf: &mut [u8]) -> Option<usize> {
+        let pay_len =
+            UBX_MSG_LEN_CFG_VAL_HEADER + self.key_count * UBX_CFG_VAL_KEY_LEN;
+        if self.overflow || buf.len() < pay_len {
+            return None;
+        }
+        // version 0: poll request
+        buf[0] = 0;
+        buf[1] = self.layer as u8;
+        write_u16(buf, 2, self.position);
+        for (idx, key_id) in self.key_ids[..self.key_count].iter().enumerate() {
+            let start = UBX_MSG_LEN_CFG_VAL_HEADER + idx * UBX_CFG_VAL_KEY_LEN;
+            write_u32(buf, start, *key_id);
+        }
+        Some(pay_len)
+    }
+}
+
+/// UBX-CFG-VALGET response: the values of the requested configuration items.
+/// `N` is the maximum length of the key-value data it can hold.
+#[derive(Copy, Clone, Debug)]
+pub struct CfgValGetResponse<const N: usize = UBX_CFG_VALGET_MAX_DATA_LEN> {
+    pub layer: ValGetLayer,
+    /// Number of values skipped before the first value in this response
+    pub position: u16,
+    /// Key-value pairs, each a little-endian u32 key ID and value
+    data: [u8; N],
+    data_len: usize,
+}
+
+impl<const N: usize> CfgValGetResponse<N> {
+    /// The stored value of a configuration item, if it's in this response
+    pub fn get<T: CfgValue>(&self, key: CfgKey<T>) -> Option<T> {
+        let raw = self.raw_value(key.id)?;
+        if raw.len() == T::SIZE {
+            Some(T::read_le(raw))
+        } else {
+            None
+        }
+    }
+
+    /// The little-endian bytes of a configuration item's value,
+    /// if it's in this response
+    pub fn raw_value(&self, key_id: u32) -> Option<&[u8]> {
+        let data = &self.data[..self.data_len];
+        let mut idx = 0;
+        while idx + UBX_CFG_VAL_KEY_LEN <= data.len() {
+            let item_key_id = read_u32(data, idx);
+            let value_start = idx + UBX_CFG_VAL_KEY_LEN;
+            let value_end = value_start + key_storage_size(item_key_id)?;
+            if value_end > data.len() {
+                return None;
+            }
+            if item_key_id == key_id {
+                return Some(&data[value_start..value_end]);
+            }
+            idx = value_end;
+        }
+        None
+    }
+}
+
+pub fn cfg_valget_from_bytes<const N: usize>(
+    buf: &[u8],
+) -> Option<CfgValGetResponse<N>> {
+    if buf.len() < UBX_MSG_LEN_CFG_VAL_HEADER {
+        return None;
+    }
+    let data_len = buf.len() - UBX_MSG_LEN_CFG_VAL_HEADER;
+    if data_len > N {
+        return None;
+    }
+    let mut response = CfgValGetResponse {
+        layer: ValGetLayer::from_u8(buf[1])?,
+        position: read_u16(buf, 2),
+        data: [0; N],
+        data_len,
+    };
+    response.data[..data_len]
+        .copy_from_slice(&buf[UBX_MSG_LEN_CFG_VAL_HEADER..]);
+    Some(response)
+}
+
+impl<const N: usize> UbxMessage for CfgValGetResponse<N> {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_CFG_VALGET;
+
+    fn from_payload(buf: &[u8]) -> Option<Self> {
+        cfg_valget_from_bytes(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valset_rejects_value_of_wrong_size_for_key() {
+        let mut buf = [0u8; 64];
+        let valid = CfgValSet::new(VAL_LAYER_RAM).with(keys::RATE_MEAS, 200);
+        assert_eq!(valid.write_payload(&mut buf), Some(10));
+
+        // CFG-RATE-MEAS is stored in two bytes, not four
+        let key = CfgKey::<u32>::new(keys::RATE_MEAS.id);
+        let invalid = CfgValSet::new(VAL_LAYER_RAM).with(key, 200);
+        assert_eq!(invalid.write_payload(&mut buf), None);
+    }
+
+    #[test]
+    fn valget_response_holds_data_longer_than_default() {
+        // 20 single-byte values: 100 bytes of key-value data
+        let mut payload = [0u8; 104];
+        payload[1] = ValGetLayer::Ram as u8;
+        for idx in 0..20 {
+            let start = UBX_MSG_LEN_CFG_VAL_HEADER + idx * 5;
+            write_u32(&mut payload, start, 0x2011_0000 + idx as u32);
+            payload[start + 4] = idx as u8;
+        }
+        let key = CfgKey::<u8>::new(0x2011_0013);
+
+        let small = cfg_valget_from_bytes::<64>(&payload);
+        assert!(small.is_none());
+        let response = cfg_valget_from_bytes::<100>(&payload).unwrap();
+        assert_eq!(response.get(key), Some(19));
+    }
+}
@@ -0,0 +1,279 @@
+use embedded_hal as hal;
+
+use super::DeviceInterface;
+use crate::cfg::PortId;
+use crate::Error;
+use shufflebuf::ShuffleBuf;
+
+/// Default 7-bit I2C address of ublox devices
+pub const DEFAULT_DDC_ADDRESS: u8 = 0x42;
+
+/// Number of bytes available for reading: high byte here, low byte at 0xFE
+const REG_BYTES_AVAIL_HIGH: u8 = 0xFD;
+/// Data stream register: reading this returns the next byte available
+const REG_DATA_STREAM: u8 = 0xFF;
+
+/// Number of bytes read from the data stream per I2C transaction
+const DDC_CHUNK_LEN: usize = 32;
+
+/// Size of our outgoing message queue: the longest message we can write
+const DDC_TX_QUEUE_LEN: usize = 256;
+
+/// This encapsulates the I2C peripheral used to communicate with
+/// the device over its DDC (I2C compatible) port
+pub struct DdcInterface<I2C>
+where
+    I2C: hal::blocking::i2c::WriteRead,
+{
+    /// the I2C port to use when communicating
+    i2c: I2C,
+    /// the 7-bit I2C address of the device
+    address: u8,
+    /// bytes received from the device, waiting to be read
+    shuffler: ShuffleBuf<256>,
+    /// bytes waiting to be sent to the device
+    tx_queue: ShuffleBuf<DDC_TX_QUEUE_LEN>,
+    /// an I2C error during `fill`, reported by the next read
+    fill_error: Option<I2C::Error>,
+}
+
+impl<I2C, CommE> DdcInterface<I2C>
+where
+    I2C: hal::blocking::i2c::Write<Error = CommE>
+        + hal::blocking::i2c::WriteRead<Error = CommE>,
+{
+    /// Communicate with a device at the default address
+    pub fn new(i2c: I2C) -> Self {
+        Self::new_with_address(i2c, DEFAULT_DDC_ADDRESS)
+    }
+
+    /// Communicate with a device at the given 7-bit address
+    pub fn new_with_address(i2c: I2C, address: u8) -> Self {
+        Self {
+            i2c,
+            address,
+            shuffler: ShuffleBuf::default(),
+            tx_queue: ShuffleBuf::default(),
+            fill_error: None,
+        }
+    }
+
+    /// Read the number of bytes the device has available for us
+    fn bytes_available(&mut self) -> Result<usize, Error<CommE>> {
+        let mut avail = [0u8; 2];
+        self.i2c
+            .write_read(self.address, &[REG_BYTES_AVAIL_HIGH], &mut avail)
+            .map_err(Error::Comm)?;
+        Ok(u16::from_be_bytes(avail) as usize)
+    }
+
+    /// Read up to `max_len` bytes from the data stream into our buffer
+    fn read_stream(&mut self, max_len: usize) -> Result<(), Error<CommE>> {
+        let mut chunk = [0u8; DDC_CHUNK_LEN];
+        let mut remaining = max_len;
+        while remaining > 0 {
+            let chunk_len = remaining.min(DDC_CHUNK_LEN);
+            self.i2c
+                .write_read(
+                    self.address,
+                    &[REG_DATA_STREAM],
+                    &mut chunk[..chunk_len],
+                )
+                .map_err(Error::Comm)?;
+            for byte in &chunk[..chunk_len] {
+                self.shuffler.push_one(*byte);
+            }
+            remaining -= chunk_len;
+        }
+        Ok(())
+    }
+}
+
+impl<I2C, CommE> DeviceInterface for DdcInterface<I2C>
+where
+    I2C: hal::blocking::i2c::Write<Error = CommE>
+        + hal::blocking::i2c::WriteRead<Error = CommE>,
+{
+    type InterfaceError = Error<CommE>;
+
+    fn fill(&mut self) -> usize {
+        let rc = self.bytes_available().and_then(|avail| {
+            let fetch_count = avail.min(self.shuffler.vacant());
+            self.read_stream(fetch_count)
+        });
+        if let Err(Error::Comm(err)) = rc {
+            self.fill_error = Some(err);
+        }
+        self.shuffler.available()
+    }
+
+    fn read(&mut self) -> Result<u8, Self::InterfaceError> {
+        if let Some(err) = self.fill_error.take() {
+            return Err(Error::Comm(err));
+        }
+        let (count, byte) = self.shuffler.read_one();
+        if count > 0 {
+            Ok(byte)
+        } else {
+            if self.bytes_available()? > 0 {
+                self.read_stream(1)?;
+            }
+            let (count, byte) = self.shuffler.read_one();
+            if count > 0 {
+                Ok(byte)
+            } else {
+                Err(Error::Unresponsive)
+            }
+        }
+    }
+
+    fn read_many(
+        &mut self,
+        buffer: &mut [u8],
+    ) -> Result<usize, Self::InterfaceError> {
+        let avail = self.shuffler.available();
+        if avail >= buffer.len() {
+            let final_read_count = self.shuffler.read_many(buffer);
+            return Ok(final_read_count);
+        }
+
+        Ok(0)
+    }
+
+    fn write(&mut self, byte: u8) -> Result<(), Self::InterfaceError> {
+        if self.tx_queue.vacant() == 0 {
+            self.flush()?;
+        }
+        self.tx_queue.push_one(byte);
+        Ok(())
+    }
+
+    fn write_many(
+        &mut self,
+        buffer: &[u8],
+    ) -> Result<usize, Self::InterfaceError> {
+        for byte in buffer {
+            self.write(*byte)?;
+        }
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::InterfaceError> {
+        // The device treats a single byte write as setting the register
+        // address, so queued bytes are sent as one transaction.
+        let mut out = [0u8; DDC_TX_QUEUE_LEN];
+        let out_len = self.tx_queue.available();
+        self.tx_queue.read_many(&mut out[..out_len]);
+        if out_len > 0 {
+            self.i2c
+                .write(self.address, &out[..out_len])
+                .map_err(Error::Comm)?;
+        }
+        Ok(())
+    }
+
+    fn port_id(&self) -> PortId {
+        PortId::Ddc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A device with `stream` bytes available, that records the length
+    /// of each data stream read
+    struct MockI2c {
+        stream: [u8; 64],
+        stream_len: usize,
+        stream_idx: usize,
+        chunk_lens: [usize; 4],
+        chunk_count: usize,
+        fail: bool,
+    }
+
+    impl MockI2c {
+        fn new(stream: &[u8]) -> Self {
+            let mut i2c = Self {
+                stream: [0; 64],
+                stream_len: stream.len(),
+                stream_idx: 0,
+                chunk_lens: [0; 4],
+                chunk_count: 0,
+                fail: false,
+            };
+            i2c.stream[..stream.len()].copy_from_slice(stream);
+            i2c
+        }
+    }
+
+    impl hal::blocking::i2c::WriteRead for MockI2c {
+        type Error = ();
+
+        fn write_read(
+            &mut self,
+            address: u8,
+            bytes: &[u8],
+            buffer: &mut [u8],
+        ) -> Result<(), ()> {
+            assert_eq!(address, DEFAULT_DDC_ADDRESS);
+            if self.fail {
+                return Err(());
+            }
+            match bytes {
+                [REG_BYTES_AVAIL_HIGH] => {
+                    let avail = (self.stream_len - self.stream_idx) as u16;
+                    buffer.copy_from_slice(&avail.to_be_bytes());
+                }
+                [REG_DATA_STREAM] => {
+                    let end = self.stream_idx + buffer.len();
+                    buffer.copy_from_slice(&self.stream[self.stream_idx..end]);
+                    self.stream_idx = end;
+                    self.chunk_lens[self.chunk_count] = buffer.len();
+                    self.chunk_count += 1;
+                }
+                _ => panic!("unexpected register"),
+            }
+            Ok(())
+        }
+    }
+
+    impl hal::blocking::i2c::Write for MockI2c {
+        type Error = ();
+
+        fn write(&mut self, _address: u8, _bytes: &[u8]) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn bytes_available_is_big_endian() {
+        let mut iface = DdcInterface::new(MockI2c::new(&[]));
+        iface.i2c.stream_len = 0x0102;
+        assert_eq!(iface.bytes_available().unwrap(), 0x0102);
+    }
+
+    #[test]
+    fn fill_reads_stream_in_chunks() {
+        let mut stream = [0u8; 40];
+        for (idx, byte) in stream.iter_mut().enumerate() {
+            *byte = idx as u8;
+        }
+        let mut iface = DdcInterface::new(MockI2c::new(&stream));
+        assert_eq!(iface.fill(), stream.len());
+        assert_eq!(iface.i2c.chunk_lens[..iface.i2c.chunk_count], [32, 8]);
+        for expected in stream {
+            assert_eq!(iface.read().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn fill_error_is_reported_by_next_read() {
+        let mut iface = DdcInterface::new(MockI2c::new(&[0xB5, 0x62]));
+        iface.i2c.fail = true;
+        assert_eq!(iface.fill(), 0);
+        iface.i2c.fail = false;
+        assert!(matches!(iface.read(), Err(Error::Comm(()))));
+        assert_eq!(iface.read().unwrap(), 0xB5);
+    }
+}
@@ -4,12 +4,19 @@ pub use self::serial::SerialInterface;
 pub mod spi;
 pub use self::spi::SpiInterface;
 
+pub mod ddc;
+pub use self::ddc::{DdcInterface, DEFAULT_DDC_ADDRESS};
+
+use crate::cfg::PortId;
+
 /// A method of communicating with the device
 pub trait DeviceInterface {
     /// Interface associated error type
     type InterfaceError;
 
     /// Fill up our buffer with unsolicited / periodic UBX messages.
+    /// Only bytes the device has already sent are read: this doesn't wait
+    /// for more, so that callers can time out when the device is silent.
     /// This function should be called before attempting to read.
     /// Returns the number of available bytes.
     fn fill(&mut self) -> usize;
@@ -24,4 +31,20 @@ pub trait DeviceInterface {
         &mut self,
         buffer: &mut [u8],
     ) -> Result<usize, Self::InterfaceError>;
+
+    /// Write a single byte to the device.
+    fn write(&mut self, byte: u8) -> Result<(), Self::InterfaceError>;
+
+    /// Write multiple bytes to the device.
+    /// Returns the number of bytes written.
+    fn write_many(
+        &mut self,
+        buffer: &[u8],
+    ) -> Result<usize, Self::InterfaceError>;
+
+    /// Block until all written bytes have been sent to the device.
+    fn flush(&mut self) -> Result<(), Self::InterfaceError>;
+
+    /// The port of the device this interface is connected to
+    fn port_id(&self) -> PortId;
 }
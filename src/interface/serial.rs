@@ -1,33 +1,50 @@
 use super::DeviceInterface;
+use crate::cfg::PortId;
 use crate::Error;
 use embedded_hal as hal;
 
 use shufflebuf::ShuffleBuf;
 
-/// This encapsulates the Serial UART peripheral
+/// This encapsulates the Serial UART peripheral (both receive and transmit)
 /// and associated pins such as
 /// - DRDY: Data Ready: Sensor uses this to indicate it had data available for read
 pub struct SerialInterface<SER> {
     /// the serial port to use when communicating
     serial: SER,
     shuffler: ShuffleBuf<256>,
+    /// the device UART the serial port is connected to
+    port_id: PortId,
 }
 
 impl<SER, CommE> SerialInterface<SER>
 where
-    SER: hal::serial::Read<u8, Error = CommE>,
+    SER: hal::serial::Read<u8, Error = CommE>
+        + hal::serial::Write<u8, Error = CommE>,
 {
+    /// Communicate with the device's first UART
     pub fn new(serial_port: SER) -> Self {
+        Self::new_with_port(serial_port, PortId::Uart1)
+    }
+
+    /// Communicate with the given device UART, eg `PortId::Uart2`
+    pub fn new_with_port(serial_port: SER, port_id: PortId) -> Self {
         Self {
             serial: serial_port,
             shuffler: ShuffleBuf::default(),
+            port_id,
         }
     }
+
+    /// Access the serial port, eg to change its baud rate
+    pub fn serial_mut(&mut self) -> &mut SER {
+        &mut self.serial
+    }
 }
 
 impl<SER, CommE> DeviceInterface for SerialInterface<SER>
 where
-    SER: hal::serial::Read<u8, Error = CommE>,
+    SER: hal::serial::Read<u8, Error = CommE>
+        + hal::serial::Write<u8, Error = CommE>,
 {
     type InterfaceError = Error<CommE>;
 
@@ -55,7 +72,9 @@ where
                     self.shuffler.push_one(byte);
                     fetch_count -= 1;
                 }
-                Err(nb::Error::WouldBlock) => {}
+                // nothing more received yet: don't wait for the buffer
+                // to fill, so that callers can time out
+                Err(nb::Error::WouldBlock) => break,
                 Err(nb::Error::Other(_)) => {
                     // in practice this is returning Overrun a ton on stm32h7
                     err_count += 1;
@@ -80,4 +99,26 @@ where
 
         Ok(0)
     }
+
+    fn write(&mut self, byte: u8) -> Result<(), Self::InterfaceError> {
+        nb::block!(self.serial.write(byte)).map_err(Error::Comm)
+    }
+
+    fn write_many(
+        &mut self,
+        buffer: &[u8],
+    ) -> Result<usize, Self::InterfaceError> {
+        for byte in buffer {
+            self.write(*byte)?;
+        }
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::InterfaceError> {
+        nb::block!(self.serial.flush()).map_err(Error::Comm)
+    }
+
+    fn port_id(&self) -> PortId {
+        self.port_id
+    }
 }
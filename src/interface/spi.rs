@@ -2,17 +2,90 @@ use embedded_hal as hal;
 use hal::digital::v2::OutputPin;
 
 use super::DeviceInterface;
+use crate::cfg::PortId;
 use crate::Error;
 use shufflebuf::ShuffleBuf;
 
+/// The device sends this byte when it has no more data to send,
+/// and the host sends it when it has no data to write.
+const SPI_IDLE_BYTE: u8 = 0xFF;
+
+/// This many consecutive idle bytes means the device has no more data,
+/// so we stop clocking out more bytes for now.
+/// 0xFF is also a valid data byte, so every received byte is kept and
+/// passed along: the UBX parser discards any that fall outside of a message.
+const SPI_IDLE_RUN_LEN: usize = 16;
+
+/// Number of bytes exchanged per SPI transaction
+const SPI_CHUNK_LEN: usize = 32;
+
 /// This encapsulates the SPI peripheral and associated pins such as:
 /// - CSN: The chip select pin
+///
+/// The ublox SPI port is full-duplex: every byte we write is exchanged for
+/// a byte the device sends, so outgoing commands are queued and sent
+/// during the same transactions used to read (see: 11.6.3 Back-To-Back
+/// Read and Write Access).
 pub struct SpiInterface<SPI, CSN> {
-    /// the serial port to use when communicating
-    _spi: SPI,
+    /// the SPI port to use when communicating
+    spi: SPI,
     /// the Chip Select pin (GPIO output) to use when communicating
-    _csn: CSN,
-    _shuffler: ShuffleBuf<256>,
+    csn: CSN,
+    /// bytes received from the device, waiting to be read
+    shuffler: ShuffleBuf<256>,
+    /// bytes waiting to be sent to the device
+    tx_queue: ShuffleBuf<256>,
+    /// number of consecutive idle bytes received from the device
+    idle_run: usize,
+}
+
+impl<SPI, CSN, CommE, PinE> SpiInterface<SPI, CSN>
+where
+    SPI: hal::blocking::spi::Write<u8, Error = CommE>
+        + hal::blocking::spi::Transfer<u8, Error = CommE>,
+    CSN: OutputPin<Error = PinE>,
+{
+    pub fn new(spi: SPI, csn: CSN) -> Self {
+        let mut inst = Self {
+            spi,
+            csn,
+            shuffler: ShuffleBuf::default(),
+            tx_queue: ShuffleBuf::default(),
+            idle_run: 0,
+        };
+        // deselect the device until we start a transaction
+        let _ = inst.csn.set_high();
+        inst
+    }
+
+    /// Exchange one chunk of bytes with the device:
+    /// send up to `max_len` queued bytes (padded with idle bytes) and
+    /// keep the bytes received.
+    /// `max_len` must not exceed the vacant space in our receive buffer,
+    /// so that no received bytes are lost.
+    /// Returns true if the device appears to have no more data to send.
+    fn transfer_chunk(&mut self, max_len: usize) -> Result<bool, Error<CommE>> {
+        let mut chunk = [SPI_IDLE_BYTE; SPI_CHUNK_LEN];
+        let chunk_len = max_len.min(SPI_CHUNK_LEN);
+        let queued = self.tx_queue.available().min(chunk_len);
+        self.tx_queue.read_many(&mut chunk[..queued]);
+
+        self.csn.set_low().map_err(|_| Error::Pin)?;
+        let rc = self.spi.transfer(&mut chunk[..chunk_len]);
+        self.csn.set_high().map_err(|_| Error::Pin)?;
+        let received = rc.map_err(Error::Comm)?;
+
+        for byte in received {
+            if *byte == SPI_IDLE_BYTE {
+                self.idle_run += 1;
+            } else {
+                self.idle_run = 0;
+            }
+            // callers limit `max_len` to the space we have for incoming bytes
+            self.shuffler.push_one(*byte);
+        }
+        Ok(self.idle_run >= SPI_IDLE_RUN_LEN)
+    }
 }
 
 impl<SPI, CSN, CommE, PinE> DeviceInterface for SpiInterface<SPI, CSN>
@@ -24,18 +97,213 @@ where
     type InterfaceError = Error<CommE>;
 
     fn fill(&mut self) -> usize {
-        // See: 11.6.3 Back-To-Back Read and Write Access
-        unimplemented!()
+        // read until our buffer is full or the device has no more data
+        loop {
+            let vacant = self.shuffler.vacant();
+            if vacant == 0 {
+                break;
+            }
+            match self.transfer_chunk(vacant) {
+                Ok(false) => {}
+                _ => break,
+            }
+        }
+        self.shuffler.available()
     }
 
     fn read(&mut self) -> Result<u8, Self::InterfaceError> {
-        unimplemented!()
+        let (count, byte) = self.shuffler.read_one();
+        if count > 0 {
+            Ok(byte)
+        } else {
+            self.transfer_chunk(self.shuffler.vacant())?;
+            let (count, byte) = self.shuffler.read_one();
+            if count > 0 {
+                Ok(byte)
+            } else {
+                Err(Error::Unresponsive)
+            }
+        }
     }
 
     fn read_many(
         &mut self,
-        _buffer: &mut [u8],
+        buffer: &mut [u8],
     ) -> Result<usize, Self::InterfaceError> {
-        unimplemented!()
+        let avail = self.shuffler.available();
+        if avail >= buffer.len() {
+            let final_read_count = self.shuffler.read_many(buffer);
+            return Ok(final_read_count);
+        }
+
+        Ok(0)
+    }
+
+    fn write(&mut self, byte: u8) -> Result<(), Self::InterfaceError> {
+        if self.tx_queue.vacant() == 0 {
+            self.flush()?;
+            if self.tx_queue.vacant() == 0 {
+                // received bytes must be read before we can send more
+                return Err(Error::MessageTooLong);
+            }
+        }
+        self.tx_queue.push_one(byte);
+        Ok(())
+    }
+
+    fn write_many(
+        &mut self,
+        buffer: &[u8],
+    ) -> Result<usize, Self::InterfaceError> {
+        for byte in buffer {
+            self.write(*byte)?;
+        }
+        Ok(buffer.len())
+    }
+
+    /// Send queued bytes, as far as there is space to keep the bytes
+    /// received in exchange. Any bytes still queued are sent by
+    /// later calls to `fill`, once received bytes have been read.
+    fn flush(&mut self) -> Result<(), Self::InterfaceError> {
+        while self.tx_queue.available() > 0 {
+            let vacant = self.shuffler.vacant();
+            if vacant == 0 {
+                break;
+            }
+            self.transfer_chunk(vacant)?;
+        }
+        Ok(())
+    }
+
+    fn port_id(&self) -> PortId {
+        PortId::Spi
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A device that always has data to send: a repeating count that
+    /// skips the idle byte
+    struct MockSpi {
+        next: u8,
+    }
+
+    impl hal::blocking::spi::Transfer<u8> for MockSpi {
+        type Error = ();
+
+        fn transfer<'w>(
+            &mut self,
+            words: &'w mut [u8],
+        ) -> Result<&'w [u8], ()> {
+            for word in words.iter_mut() {
+                *word = self.next;
+                self.next = (self.next + 1) % SPI_IDLE_BYTE;
+            }
+            Ok(words)
+        }
+    }
+
+    impl hal::blocking::spi::Write<u8> for MockSpi {
+        type Error = ();
+
+        fn write(&mut self, _words: &[u8]) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    /// A device that sends a scripted byte stream, then idles
+    struct ScriptedSpi {
+        script: [u8; 64],
+        script_len: usize,
+        idx: usize,
+    }
+
+    impl hal::blocking::spi::Transfer<u8> for ScriptedSpi {
+        type Error = ();
+
+        fn transfer<'w>(
+            &mut self,
+            words: &'w mut [u8],
+        ) -> Result<&'w [u8], ()> {
+            for word in words.iter_mut() {
+                *word = if self.idx < self.script_len {
+                    self.script[self.idx]
+                } else {
+                    SPI_IDLE_BYTE
+                };
+                self.idx += 1;
+            }
+            Ok(words)
+        }
+    }
+
+    impl hal::blocking::spi::Write<u8> for ScriptedSpi {
+        type Error = ();
+
+        fn write(&mut self, _words: &[u8]) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    struct MockPin;
+
+    impl OutputPin for MockPin {
+        type Error = ();
+
+        fn set_low(&mut self) -> Result<(), ()> {
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn flush_keeps_received_bytes_when_buffer_is_full() {
+        let mut iface = SpiInterface::new(MockSpi { next: 0 }, MockPin);
+        let mut expected = 0u8;
+        for _ in 0..4 {
+            iface.fill();
+            iface.write_many(&[0xB5; 40]).unwrap();
+            iface.flush().unwrap();
+            // every byte received, in order, with none dropped
+            while let (1, byte) = iface.shuffler.read_one() {
+                assert_eq!(byte, expected);
+                expected = (expected + 1) % SPI_IDLE_BYTE;
+            }
+        }
+    }
+
+    #[test]
+    fn idle_valued_payload_bytes_are_kept() {
+        // a long run of idle-valued bytes inside a frame's payload
+        let mut payload = [0u8; 40];
+        payload[4..36].fill(SPI_IDLE_BYTE);
+        let mut script = [0u8; 64];
+        let script_len =
+            crate::encode_ubx_frame(0x0A, 0x04, &payload, &mut script).unwrap();
+        let spi = ScriptedSpi {
+            script,
+            script_len,
+            idx: 0,
+        };
+        let mut iface = SpiInterface::new(spi, MockPin);
+        let mut parser = crate::UbxParser::<64>::new();
+
+        let mut header = None;
+        for _ in 0..4 {
+            let available = iface.fill();
+            for _ in 0..available {
+                let byte = iface.read().unwrap();
+                if let Some(hdr) = parser.push(byte) {
+                    header = Some(hdr);
+                }
+            }
+        }
+        assert_eq!(header.unwrap().msg_unique_id(), 0x0A04);
+        assert_eq!(parser.payload(), &payload[..]);
     }
 }
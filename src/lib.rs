@@ -4,16 +4,30 @@ LICENSE: BSD3 (see LICENSE file)
 */
 
 #![no_std]
+#![deny(unsafe_code)]
 
 use embedded_hal as hal;
 
 mod interface;
-pub use interface::{DeviceInterface, SerialInterface};
+pub use interface::{
+    DdcInterface, DeviceInterface, SerialInterface, SpiInterface,
+    DEFAULT_DDC_ADDRESS,
+};
 
 use hal::blocking::delay::DelayUs;
 
-mod messages;
+pub mod messages;
 use messages::*;
+pub use messages::{encode_ubx_frame, ubx_checksum, UbxCommand, UbxMessage};
+
+pub mod cfg;
+use cfg::*;
+
+pub mod cfgval;
+use cfgval::*;
+
+mod parser;
+pub use parser::{UbxFrameHeader, UbxParser};
 
 /// Errors in this crate
 #[derive(Debug)]
@@ -23,26 +37,138 @@ pub enum Error<CommE> {
 
     /// Sensor is not responding
     Unresponsive,
+
+    /// Sensor rejected a configuration message (UBX-ACK-NAK)
+    Nak,
+
+    /// Sensor accepted a configuration message, but its configuration
+    /// doesn't match what was sent
+    ConfigMismatch,
+
+    /// Message is too long to fit in the buffer provided
+    MessageTooLong,
+
+    /// The requested operation isn't supported by the sensor or interface
+    Unsupported,
+
+    /// Unable to set a pin, eg the SPI chip select
+    Pin,
+
+    /// Received a message that could not be decoded
+    InvalidMessage,
 }
 
+/// Create a new driver that communicates with the device over a UART.
+/// The UART must support both reading and writing, so that the driver
+/// can send configuration and poll messages to the device.
 pub fn new_serial_driver<UART, CommE>(
     uart: UART,
 ) -> UbxDriver<SerialInterface<UART>>
 where
-    UART: hal::serial::Read<u8, Error = CommE>,
+    UART: hal::serial::Read<u8, Error = CommE>
+        + hal::serial::Write<u8, Error = CommE>,
     CommE: core::fmt::Debug,
 {
     let iface = interface::SerialInterface::new(uart);
     UbxDriver::new_with_interface(iface)
 }
 
-/// Read buffer size based on maximum UBX message size we support
-const READ_BUF_LEN: usize = 128;
+/// Create a new driver that communicates with the device over SPI.
+/// `csn` is the chip select pin for the device.
+pub fn new_spi_driver<SPI, CSN, CommE, PinE>(
+    spi: SPI,
+    csn: CSN,
+) -> UbxDriver<SpiInterface<SPI, CSN>>
+where
+    SPI: hal::blocking::spi::Write<u8, Error = CommE>
+        + hal::blocking::spi::Transfer<u8, Error = CommE>,
+    CSN: hal::digital::v2::OutputPin<Error = PinE>,
+    CommE: core::fmt::Debug,
+{
+    let iface = interface::SpiInterface::new(spi, csn);
+    UbxDriver::new_with_interface(iface)
+}
+
+/// Create a new driver that communicates with the device over I2C (DDC),
+/// at the given 7-bit address (usually `DEFAULT_DDC_ADDRESS`)
+pub fn new_ddc_driver<I2C, CommE>(
+    i2c: I2C,
+    address: u8,
+) -> UbxDriver<DdcInterface<I2C>>
+where
+    I2C: hal::blocking::i2c::Write<Error = CommE>
+        + hal::blocking::i2c::WriteRead<Error = CommE>,
+    CommE: core::fmt::Debug,
+{
+    let iface = interface::DdcInterface::new_with_address(i2c, address);
+    UbxDriver::new_with_interface(iface)
+}
+
+/// Default read buffer size: the largest UBX payload the driver can parse.
+/// Longer messages are skipped.
+pub const DEFAULT_READ_BUF_LEN: usize = 128;
+
+/// Write buffer size based on the largest UBX message we send
+const WRITE_BUF_LEN: usize = 128;
+
+/// Default time to wait for the device to respond to a command (microseconds)
+pub const DEFAULT_RESPONSE_TIMEOUT_US: u32 = 1_000_000;
+
+/// Default number of times to resend a command the device hasn't acknowledged
+pub const DEFAULT_ACK_RETRIES: u8 = 2;
 
-pub struct UbxDriver<DI> {
+/// How long to wait between checks for a response (microseconds)
+const RESPONSE_POLL_INTERVAL_US: u32 = 1000;
+
+/// How long to wait after handling each message, before checking for
+/// the next one (microseconds)
+const MESSAGE_HANDLE_DELAY_US: u32 = 1000;
+
+/// How long to wait for the device to switch baud rates (microseconds)
+const BAUD_RATE_SWITCH_DELAY_US: u32 = 100_000;
+
+/// How long to wait for the device to restart after a reset (microseconds)
+const RESET_SETTLE_DELAY_US: u32 = 1_000_000;
+
+/// Number of 0xFF bytes sent to wake a UART from power save mode
+const UART_WAKE_UP_LEN: usize = 8;
+
+/// How long to wait after the wake-up sequence (or selecting the device,
+/// on SPI) before sending a command (microseconds)
+const WAKE_UP_DELAY_US: u32 = 100_000;
+
+/// Baud rates to try when detecting the baud rate of the device:
+/// the factory default first, then other common rates
+pub const DEFAULT_BAUD_CANDIDATES: [u32; 7] =
+    [9600, 38400, 115200, 57600, 230400, 19200, 460800];
+
+/// Default time to listen for valid messages at each candidate baud rate
+/// (microseconds): long enough to see output sent once per second
+pub const DEFAULT_AUTOBAUD_WINDOW_US: u32 = 1_200_000;
+
+/// Length of an NMEA sentence prefix such as "$GPGGA,"
+const NMEA_PREFIX_LEN: usize = 7;
+
+/// Periodic output rates of the messages configured by `setup`,
+/// relative to the navigation rate
+const SETUP_MSG_RATES: [(u16, u8); 3] = [
+    (UBX_MSG_ID_NAV_PVT, 1),
+    (UBX_MSG_ID_NAV_DOP, 1),
+    (UBX_MSG_ID_MON_HW, 5),
+];
+
+/// Navigation measurement period configured by `setup` (ms)
+const SETUP_MEAS_RATE_MS: u16 = 200;
+
+/// Driver for a ublox device.
+/// `BUF_LEN` is the largest UBX payload the driver can parse:
+/// increase it to handle long messages such as UBX-NAV-SAT,
+/// which needs 8 bytes plus 12 per satellite (eg 872 for 72 satellites).
+pub struct UbxDriver<DI, const BUF_LEN: usize = DEFAULT_READ_BUF_LEN> {
     /// the device interface
     di: DI,
-    read_buf: [u8; READ_BUF_LEN],
+    /// Parses incoming bytes into UBX frames
+    parser: UbxParser<BUF_LEN>,
 
     /// The last received UBX-NAV-PVT from the device, if any
     last_nav_pvt: Option<NavPosVelTimeM8>,
@@ -50,31 +176,130 @@ pub struct UbxDriver<DI> {
     last_mon_hw: Option<MonHardwareM8>,
     /// The last received UBX-NAV-DOP from the device, if any
     last_nav_dop: Option<NavDopM8>,
+    /// The last received UBX-NAV-STATUS from the device, if any
+    last_nav_status: Option<NavStatusM8>,
+    /// The last received UBX-TIM-TP from the device, if any
+    last_tim_tp: Option<TimTpM8>,
+    /// The last received UBX-TIM-TM2 from the device, if any
+    last_tim_tm2: Option<TimTm2M8>,
+    /// The command we're waiting for the device to acknowledge, if any
+    ack_pending: Option<u16>,
+    /// Whether the device acknowledged (true) or rejected (false)
+    /// the pending command, once it has answered
+    ack_result: Option<bool>,
+    /// How long to wait for the device to acknowledge a command
+    ack_timeout_us: u32,
+    /// How many times to resend a command the device hasn't acknowledged
+    ack_retries: u8,
+    /// How long to wait for the device to answer a poll request
+    poll_timeout_us: u32,
+    /// Whether the device is in power save mode, and may be asleep
+    /// when we send it a command
+    power_save: bool,
+    /// Whether we've put the device in backup mode (UBX-RXM-PMREQ)
+    asleep: bool,
 }
 
-impl<DI, CommE> UbxDriver<DI>
+impl<DI, CommE, const BUF_LEN: usize> UbxDriver<DI, BUF_LEN>
 where
     DI: DeviceInterface<InterfaceError = Error<CommE>>,
     CommE: core::fmt::Debug,
 {
-    pub(crate) fn new_with_interface(device_interface: DI) -> Self {
+    /// Create a new driver using the given device interface
+    pub fn new_with_interface(device_interface: DI) -> Self {
         Self {
             di: device_interface,
-            read_buf: [0; READ_BUF_LEN],
+            parser: UbxParser::new(),
             last_nav_pvt: None,
             last_mon_hw: None,
             last_nav_dop: None,
+            last_nav_status: None,
+            last_tim_tp: None,
+            last_tim_tm2: None,
+            ack_pending: None,
+            ack_result: None,
+            ack_timeout_us: DEFAULT_RESPONSE_TIMEOUT_US,
+            ack_retries: DEFAULT_ACK_RETRIES,
+            poll_timeout_us: DEFAULT_RESPONSE_TIMEOUT_US,
+            power_save: false,
+            asleep: false,
         }
     }
 
+    /// Configure the device for the messages this driver handles:
+    /// - UBX-only output on the port we're connected to
+    /// - Periodic UBX-NAV-PVT, UBX-NAV-DOP and UBX-MON-HW output
+    /// - Navigation rate
+    ///
+    /// The legacy UBX-CFG messages are tried first: if the device rejects
+    /// the initial UBX-CFG-PRT poll (eg an M10), the same configuration is
+    /// applied with UBX-CFG-VALSET instead.
+    /// Each configuration message must be acknowledged by the device:
+    /// returns `Error::Nak` if the device rejects one, or
+    /// `Error::Unresponsive` if it doesn't answer in time.
     pub fn setup(
         &mut self,
-        _delay_source: &mut impl DelayUs<u32>,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        // poll the configuration of the port we're connected to
+        match self.poll::<CfgPrt>(delay_source) {
+            Ok(port_cfg) => self.setup_legacy(port_cfg, delay_source),
+            // generation 9 and later devices may not support the
+            // legacy configuration messages
+            Err(Error::Nak) => self.setup_key_value(delay_source),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// `setup` using the legacy UBX-CFG messages (M8 and earlier),
+    /// starting from the current configuration of our port
+    fn setup_legacy(
+        &mut self,
+        mut port_cfg: CfgPrt,
+        delay_source: &mut impl DelayUs<u32>,
     ) -> Result<(), DI::InterfaceError> {
-        //TODO configure ublox sensor using CFG message
+        // only UBX output on this port, leaving baud rate etc unchanged
+        port_cfg.out_proto_mask = PROTO_MASK_UBX;
+        self.send_command_and_wait_ack(&port_cfg, delay_source)?;
+
+        // periodic output of the messages we handle, on this port
+        for (msg_unique_id, rate) in SETUP_MSG_RATES {
+            let cmd = CfgMsgCurrentPort {
+                msg_unique_id,
+                rate,
+            };
+            self.send_command_and_wait_ack(&cmd, delay_source)?;
+        }
+
+        // one navigation solution per measurement, aligned to GPS time
+        let rate = CfgRate::new(SETUP_MEAS_RATE_MS);
+        self.send_command_and_wait_ack(&rate, delay_source)?;
+
         Ok(())
     }
 
+    /// `setup` using key-value configuration (generation 9 and later),
+    /// applied to the RAM layer only
+    fn setup_key_value(
+        &mut self,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        let port = self.di.port_id();
+        let mut valset = CfgValSet::new(VAL_LAYER_RAM)
+            .with(out_prot_ubx_key(port), true)
+            .with(out_prot_nmea_key(port), false);
+        for (msg_unique_id, rate) in SETUP_MSG_RATES {
+            if let Some(key) = msg_out_key(msg_unique_id, port) {
+                valset = valset.with(key, rate);
+            }
+        }
+        let valset = valset
+            .with(keys::RATE_MEAS, SETUP_MEAS_RATE_MS)
+            .with(keys::RATE_NAV, 1)
+            .with(keys::RATE_TIMEREF, TimeRef::Gps.to_u16() as u8);
+        self.set_config(&valset, delay_source)
+    }
+
     pub fn take_last_nav_pvt(&mut self) -> Option<NavPosVelTimeM8> {
         self.last_nav_pvt.take()
     }
@@ -87,104 +312,702 @@ where
         self.last_mon_hw.take()
     }
 
-    /// generate a 16 bit checksum for a payload
-    fn checksum_for_payload(
+    pub fn take_last_nav_status(&mut self) -> Option<NavStatusM8> {
+        self.last_nav_status.take()
+    }
+
+    /// The time of the next time pulse: UBX-TIM-TP is sent before the
+    /// pulse it describes, so pair it with the next pulse edge captured
+    /// after it was received.
+    /// Enable its output first, eg with `CfgMsgCurrentPort`.
+    pub fn take_last_tim_tp(&mut self) -> Option<TimTpM8> {
+        self.last_tim_tp.take()
+    }
+
+    /// The last time mark from an EXTINT pin: check `new_rising_edge` and
+    /// `new_falling_edge` for which edges are new since the previous mark.
+    /// Enable its output first, eg with `CfgMsgCurrentPort`.
+    pub fn take_last_tim_tm2(&mut self) -> Option<TimTm2M8> {
+        self.last_tim_tm2.take()
+    }
+
+    /// Send a UBX message with the given payload to the device
+    fn send_ubx_message(
+        &mut self,
+        msg_unique_id: u16,
         payload: &[u8],
-        _dump_ck: bool,
-    ) -> [u8; UBX_CKSUM_LEN] {
-        let mut checksum = [0u8; UBX_CKSUM_LEN];
-        for word in payload {
-            checksum[0] = checksum[0].wrapping_add(*word);
-            checksum[1] = checksum[1].wrapping_add(checksum[0]);
+    ) -> Result<(), DI::InterfaceError> {
+        let mut write_buf = [0u8; WRITE_BUF_LEN];
+        let msg_len = encode_ubx_frame(
+            (msg_unique_id >> 8) as u8,
+            (msg_unique_id & 0xFF) as u8,
+            payload,
+            &mut write_buf,
+        )
+        .ok_or(Error::MessageTooLong)?;
+        self.di.write_many(&write_buf[..msg_len])?;
+        self.di.flush()
+    }
+
+    /// Send a typed UBX message, such as a UBX-CFG message, to the device
+    pub fn send_command<C: UbxCommand>(
+        &mut self,
+        cmd: &C,
+    ) -> Result<(), DI::InterfaceError> {
+        let mut payload = [0u8; WRITE_BUF_LEN];
+        let pay_len = cmd
+            .write_payload(&mut payload)
+            .ok_or(Error::MessageTooLong)?;
+        self.send_ubx_message(C::MSG_UNIQUE_ID, &payload[..pay_len])
+    }
+
+    /// Send a typed UBX-CFG message to the device and wait for it to be
+    /// acknowledged: see `send_and_wait_ack`
+    pub fn send_command_and_wait_ack<C: UbxCommand>(
+        &mut self,
+        cmd: &C,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        let mut payload = [0u8; WRITE_BUF_LEN];
+        let pay_len = cmd
+            .write_payload(&mut payload)
+            .ok_or(Error::MessageTooLong)?;
+        self.send_and_wait_ack(
+            C::MSG_UNIQUE_ID,
+            &payload[..pay_len],
+            delay_source,
+        )
+    }
+
+    /// Set how long to wait for the device to acknowledge a command
+    pub fn set_ack_timeout_us(&mut self, timeout_us: u32) {
+        self.ack_timeout_us = timeout_us;
+    }
+
+    /// Set how many times to resend a command the device hasn't acknowledged
+    pub fn set_ack_retries(&mut self, retries: u8) {
+        self.ack_retries = retries;
+    }
+
+    /// Send a UBX-CFG message to the device and wait for it to be
+    /// acknowledged, resending it if the device doesn't answer in time.
+    /// Returns `Error::Nak` if the device rejects the message, or
+    /// `Error::Unresponsive` if it never answers.
+    pub fn send_and_wait_ack(
+        &mut self,
+        msg_unique_id: u16,
+        payload: &[u8],
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        let rc = self.send_and_wait_ack_retries(
+            msg_unique_id,
+            payload,
+            delay_source,
+        );
+        self.ack_pending = None;
+        rc
+    }
+
+    fn send_and_wait_ack_retries(
+        &mut self,
+        msg_unique_id: u16,
+        payload: &[u8],
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        for _attempt in 0..=self.ack_retries {
+            self.ack_pending = Some(msg_unique_id);
+            self.ack_result = None;
+            self.wake_if_power_save(delay_source)?;
+            self.send_ubx_message(msg_unique_id, payload)?;
+            let mut elapsed = 0;
+            while elapsed < self.ack_timeout_us {
+                let handled = self.handle_all_messages(delay_source)?;
+                if let Some(acknowledged) = self.ack_result.take() {
+                    return if acknowledged {
+                        Ok(())
+                    } else {
+                        Err(Error::Nak)
+                    };
+                }
+                // time spent handling other messages counts too
+                let handled_us =
+                    (handled as u32).saturating_mul(MESSAGE_HANDLE_DELAY_US);
+                delay_source.delay_us(RESPONSE_POLL_INTERVAL_US);
+                elapsed = elapsed
+                    .saturating_add(handled_us)
+                    .saturating_add(RESPONSE_POLL_INTERVAL_US);
+            }
         }
-        checksum
+        Err(Error::Unresponsive)
     }
 
-    /// Read our interface for a message of known size
-    ///
-    fn read_ubx_message(
-        &mut self,
-        msg_len: usize,
-        dump_ck: bool,
-    ) -> Result<(bool, usize), DI::InterfaceError> {
-        // The length sent in the header is defined as being that of the payload only.
-        // It does not include the Preamble, Message Class, Message ID, Length, or CRC fields.
-        // The number format of the length field is a Little-Endian unsigned 16-bit integer.
-
-        let max_pay_idx = UBX_HEADER_LEN + msg_len;
-        let max_msg_idx = max_pay_idx + UBX_CKSUM_LEN;
-        let desired_count = max_msg_idx - UBX_HEADER_LEN;
-        self.read_buf[max_msg_idx] = 0;
-        let read_count = self
-            .di
-            .read_many(&mut self.read_buf[UBX_HEADER_LEN..max_msg_idx])?;
-        if read_count < desired_count {
-            // unable to read enough bytes to fill the message struct
-            return Ok((false, 0));
-        }
-        let calc_ck =
-            Self::checksum_for_payload(&self.read_buf[..max_pay_idx], dump_ck);
-        let recvd_ck =
-            &self.read_buf[(max_msg_idx - UBX_CKSUM_LEN)..max_msg_idx];
-        let matches = calc_ck[0] == recvd_ck[0] && calc_ck[1] == recvd_ck[1];
-        if matches {
-            Ok((true, max_pay_idx))
-        } else {
-            Ok((false, 0))
+    /// Set how long to wait for the device to answer a poll request
+    pub fn set_poll_timeout_us(&mut self, timeout_us: u32) {
+        self.poll_timeout_us = timeout_us;
+    }
+
+    /// Poll the device for the current value of message `M`,
+    /// and wait for the answer.
+    /// Other messages received while waiting are handled as usual.
+    /// The response must fit in the driver's `BUF_LEN`,
+    /// otherwise `Error::MessageTooLong` is returned.
+    pub fn poll<M: UbxMessage>(
+        &mut self,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<M, DI::InterfaceError> {
+        let payload = self.poll_raw(M::MSG_UNIQUE_ID, &[], delay_source)?;
+        M::from_payload(payload).ok_or(Error::InvalidMessage)
+    }
+
+    /// Send a poll request for the given message, with an optional
+    /// payload (some messages, such as UBX-CFG-PRT, accept parameters
+    /// when polled), and wait for the device to answer.
+    /// Returns the payload of the answer, `Error::Nak` if the device
+    /// rejected the request, or `Error::MessageTooLong` if the answer
+    /// doesn't fit in the driver's `BUF_LEN`.
+    pub fn poll_raw(
+        &mut self,
+        msg_unique_id: u16,
+        poll_payload: &[u8],
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<&[u8], DI::InterfaceError> {
+        // the device may reject the request with a UBX-ACK-NAK
+        self.ack_pending = Some(msg_unique_id);
+        self.ack_result = None;
+        let rc = self.wait_for_poll_answer(
+            msg_unique_id,
+            poll_payload,
+            delay_source,
+        );
+        self.ack_pending = None;
+        rc?;
+        Ok(self.parser.payload())
+    }
+
+    fn wait_for_poll_answer(
+        &mut self,
+        msg_unique_id: u16,
+        poll_payload: &[u8],
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        self.wake_if_power_save(delay_source)?;
+        // forget any frame skipped before our request was sent
+        self.parser.take_skipped();
+        self.send_ubx_message(msg_unique_id, poll_payload)?;
+        let mut elapsed = 0;
+        loop {
+            let header = self.handle_one_frame()?;
+            // the answer may have been skipped for being too long
+            if let Some(skipped) = self.parser.take_skipped() {
+                if skipped.msg_unique_id() == msg_unique_id {
+                    return Err(Error::MessageTooLong);
+                }
+            }
+            if let Some(header) = header {
+                if header.msg_unique_id() == msg_unique_id {
+                    return Ok(());
+                }
+                if self.ack_result == Some(false) {
+                    return Err(Error::Nak);
+                }
+            }
+            // other messages mustn't keep us waiting past the timeout
+            if elapsed >= self.poll_timeout_us {
+                return Err(Error::Unresponsive);
+            }
+            if header.is_none() {
+                delay_source.delay_us(RESPONSE_POLL_INTERVAL_US);
+            }
+            elapsed = elapsed.saturating_add(RESPONSE_POLL_INTERVAL_US);
         }
     }
 
-    /// Read a UBX-NAV-PVT message from the device
-    fn handle_msg_nav_pvt(&mut self) -> Result<(), DI::InterfaceError> {
-        let (ck_ok, max_pay_idx) =
-            self.read_ubx_message(UBX_MSG_LEN_NAV_PVT, false)?;
-        if ck_ok {
-            self.last_nav_pvt = messages::nav_pvt_from_bytes(
-                &self.read_buf[UBX_HEADER_LEN..max_pay_idx],
-            );
+    /// Set the navigation/measurement rate, and verify it was applied
+    pub fn configure_rate(
+        &mut self,
+        rate: &CfgRate,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        self.apply_and_verify(rate, delay_source, |sent, current| {
+            sent == current
+        })
+    }
+
+    /// Set navigation engine settings such as the dynamic platform model,
+    /// and verify they were applied
+    pub fn configure_nav5(
+        &mut self,
+        nav5: &CfgNav5,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        self.apply_and_verify(nav5, delay_source, CfgNav5::is_applied_in)
+    }
+
+    /// Select which GNSS (constellations) the device uses, and how many
+    /// tracking channels each may use.
+    /// The current configuration is polled first, and `Error::Unsupported`
+    /// returned if the device doesn't support the requested configuration.
+    /// The device may restart GNSS tracking to apply the new configuration.
+    pub fn configure_gnss(
+        &mut self,
+        gnss: &CfgGnss,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        let current: CfgGnss = self.poll(delay_source)?;
+        if !gnss.is_supported_by(&current) {
+            return Err(Error::Unsupported);
         }
+        self.send_command_and_wait_ack(gnss, delay_source)
+    }
+
+    /// Set the values of configuration items (generation 9 and later):
+    /// the device acknowledges each UBX-CFG-VALSET, including those
+    /// within a transaction.
+    pub fn set_config(
+        &mut self,
+        valset: &CfgValSet,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        self.send_command_and_wait_ack(valset, delay_source)
+    }
+
+    /// Read the values of configuration items (generation 9 and later).
+    /// Returns `Error::Nak` if the device doesn't know any of the keys,
+    /// or `Error::MessageTooLong` if the response doesn't fit in `BUF_LEN`.
+    pub fn get_config(
+        &mut self,
+        valget: &CfgValGet,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<CfgValGetResponse<BUF_LEN>, DI::InterfaceError> {
+        let mut request = [0u8; WRITE_BUF_LEN];
+        let request_len = valget
+            .write_payload(&mut request)
+            .ok_or(Error::MessageTooLong)?;
+        let payload = self.poll_raw(
+            UBX_MSG_ID_CFG_VALGET,
+            &request[..request_len],
+            delay_source,
+        )?;
+        cfg_valget_from_bytes(payload).ok_or(Error::InvalidMessage)
+    }
+
+    /// Read the value of a single configuration item from `layer`
+    pub fn get_config_value<T: CfgValue>(
+        &mut self,
+        layer: ValGetLayer,
+        key: CfgKey<T>,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<T, DI::InterfaceError> {
+        let valget = CfgValGet::new(layer).with_key(key);
+        let response = self.get_config(&valget, delay_source)?;
+        response.get(key).ok_or(Error::InvalidMessage)
+    }
+
+    /// Delete configuration items from the BBR and/or flash layers
+    /// (generation 9 and later)
+    pub fn delete_config(
+        &mut self,
+        valdel: &CfgValDel,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        self.send_command_and_wait_ack(valdel, delay_source)
+    }
+
+    /// Enter power save mode with the given settings, eg
+    /// `CfgPm2::cyclic_tracking`.
+    /// While in power save mode the driver sends the UART wake-up
+    /// sequence before each command, as the device may be asleep.
+    pub fn enter_power_save(
+        &mut self,
+        pm2: &CfgPm2,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        self.send_command_and_wait_ack(pm2, delay_source)?;
+        let rxm = CfgRxm {
+            lp_mode: LowPowerMode::PowerSave,
+        };
+        self.send_command_and_wait_ack(&rxm, delay_source)?;
+        self.power_save = true;
+        Ok(())
+    }
+
+    /// Return to continuous (full power) mode
+    pub fn leave_power_save(
+        &mut self,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        let rxm = CfgRxm {
+            lp_mode: LowPowerMode::Continuous,
+        };
+        self.send_command_and_wait_ack(&rxm, delay_source)?;
+        self.power_save = false;
         Ok(())
     }
 
-    /// Read a UBX-NAV-DOP message from the device
-    fn handle_msg_nav_dop(&mut self) -> Result<(), DI::InterfaceError> {
-        let (ck_ok, max_pay_idx) =
-            self.read_ubx_message(UBX_MSG_LEN_NAV_DOP, true)?;
-        if ck_ok {
-            self.last_nav_dop = messages::nav_dop_from_bytes(
-                &self.read_buf[UBX_HEADER_LEN..max_pay_idx],
-            );
+    /// Whether the driver has put the device in power save mode
+    pub fn is_power_save(&self) -> bool {
+        self.power_save
+    }
+
+    /// Put the device in backup mode, eg `RxmPmReqM8::backup`, until the
+    /// requested duration ends or one of the wakeup sources fires.
+    /// While the device is asleep the driver doesn't read from it,
+    /// so that `handle_one_message` returns no messages (rather than an
+    /// error) and our reads don't wake it:
+    /// call `wake_from_sleep` before using the device again.
+    pub fn request_sleep(
+        &mut self,
+        pmreq: &RxmPmReqM8,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        // in power save mode the device may ignore the request unless woken
+        self.wake_if_power_save(delay_source)?;
+        self.send_command(pmreq)?;
+        self.asleep = true;
+        Ok(())
+    }
+
+    /// Whether the driver has put the device in backup mode
+    pub fn is_asleep(&self) -> bool {
+        self.asleep
+    }
+
+    /// Wake the device from backup mode, or resume talking to it after the
+    /// requested sleep duration has ended.
+    /// Any partial message received before the device went to sleep is
+    /// discarded.
+    pub fn wake_from_sleep(
+        &mut self,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        self.asleep = false;
+        self.wake_up(delay_source)?;
+        self.discard_input()
+    }
+
+    /// Wake the device from power save or backup mode, so that it accepts
+    /// commands.
+    /// On a UART the device discards the bytes that wake it, so we send a
+    /// sequence of 0xFF bytes and give it time to wake up.
+    /// On SPI, selecting the device wakes it.
+    /// Over I2C (DDC) the device wakes in power save mode when addressed,
+    /// but it can't be woken from backup mode.
+    pub fn wake_up(
+        &mut self,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        match self.di.port_id() {
+            PortId::Uart1 | PortId::Uart2 => {
+                self.di.write_many(&[0xFF; UART_WAKE_UP_LEN])?;
+                self.di.flush()?;
+                delay_source.delay_us(WAKE_UP_DELAY_US);
+            }
+            PortId::Spi => {
+                // any transaction selects the device
+                self.di.fill();
+                delay_source.delay_us(WAKE_UP_DELAY_US);
+            }
+            _ => {}
         }
         Ok(())
     }
 
-    /// Read a UBX-MON-HW message from the device
-    fn handle_msg_mon_hw(&mut self) -> Result<(), DI::InterfaceError> {
-        let (ck_ok, max_pay_idx) =
-            self.read_ubx_message(UBX_MSG_LEN_MON_HW, false)?;
-        if ck_ok {
-            self.last_mon_hw = messages::mon_hw_from_bytes(
-                &self.read_buf[UBX_HEADER_LEN..max_pay_idx],
-            );
+    /// Before sending a command: wake the device if it may be asleep
+    fn wake_if_power_save(
+        &mut self,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        if self.asleep {
+            self.wake_from_sleep(delay_source)?;
+        } else if self.power_save {
+            self.wake_up(delay_source)?;
         }
         Ok(())
     }
 
-    /// Handle a message we don't recognize, by reading past it
-    fn skip_unhandled_msg(&mut self) -> Result<(), DI::InterfaceError> {
-        // The length sent in the header is defined as being that of the payload only.
-        // It does not include the Preamble, Message Class, Message ID, Length, or CRC fields.
-        // The number format of the length field is a Little-Endian unsigned 16-bit integer.
-        let msg_len = ((self.read_buf[2] as u16)
-            + ((self.read_buf[3] as u16) << 8)) as usize;
-        let max_pay_idx = UBX_HEADER_LEN + msg_len;
-        let max_msg_idx = (max_pay_idx + UBX_CKSUM_LEN).min(READ_BUF_LEN);
-        self.di
-            .read_many(&mut self.read_buf[UBX_HEADER_LEN..max_msg_idx])?;
+    /// Save the current configuration `sections` (see `cfg::CFG_SECTION_*`)
+    /// to non-volatile `devices` (see `cfg::CFG_DEVICE_*`), eg after `setup`,
+    /// so that the device starts with this configuration after power loss.
+    pub fn save_config(
+        &mut self,
+        sections: u32,
+        devices: u8,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        let cmd = CfgCfg::save(sections, devices);
+        self.send_command_and_wait_ack(&cmd, delay_source)
+    }
 
+    /// Load the configuration `sections` saved in non-volatile `devices`
+    pub fn load_config(
+        &mut self,
+        sections: u32,
+        devices: u8,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        let cmd = CfgCfg::load(sections, devices);
+        self.send_command_and_wait_ack(&cmd, delay_source)
+    }
+
+    /// Clear the configuration `sections` saved in non-volatile `devices`
+    /// and restore their defaults.
+    /// Note that this may reset the port configuration (eg baud rate) too,
+    /// unless `cfg::CFG_SECTION_IO_PORT` is excluded from `sections`.
+    pub fn reset_config_to_default(
+        &mut self,
+        sections: u32,
+        devices: u8,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        let cmd = CfgCfg::reset_to_default(sections, devices);
+        self.send_command_and_wait_ack(&cmd, delay_source)
+    }
+
+    /// Send a configuration message, wait for it to be acknowledged,
+    /// then poll the current configuration and check it with `verify`
+    fn apply_and_verify<C, F>(
+        &mut self,
+        cfg: &C,
+        delay_source: &mut impl DelayUs<u32>,
+        verify: F,
+    ) -> Result<(), DI::InterfaceError>
+    where
+        C: UbxCommand + UbxMessage,
+        F: FnOnce(&C, &C) -> bool,
+    {
+        self.send_command_and_wait_ack(cfg, delay_source)?;
+        let current: C = self.poll(delay_source)?;
+        if verify(cfg, &current) {
+            Ok(())
+        } else {
+            Err(Error::ConfigMismatch)
+        }
+    }
+
+    /// Poll the device for the configuration of the given port
+    pub fn poll_port_config(
+        &mut self,
+        port_id: PortId,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<CfgPrt, DI::InterfaceError> {
+        let payload =
+            self.poll_raw(UBX_MSG_ID_CFG_PRT, &[port_id as u8], delay_source)?;
+        cfg_prt_from_bytes(payload).ok_or(Error::InvalidMessage)
+    }
+
+    /// Poll the device for the configuration of the given time pulse
+    pub fn poll_timepulse_config(
+        &mut self,
+        tp_idx: TimePulseId,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<CfgTp5, DI::InterfaceError> {
+        let payload =
+            self.poll_raw(UBX_MSG_ID_CFG_TP5, &[tp_idx as u8], delay_source)?;
+        cfg_tp5_from_bytes(payload).ok_or(Error::InvalidMessage)
+    }
+
+    /// Configure a time pulse output, and verify it was applied
+    pub fn configure_timepulse(
+        &mut self,
+        tp5: &CfgTp5,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        self.send_command_and_wait_ack(tp5, delay_source)?;
+        let current = self.poll_timepulse_config(tp5.tp_idx, delay_source)?;
+        if tp5.is_applied_in(&current) {
+            Ok(())
+        } else {
+            Err(Error::ConfigMismatch)
+        }
+    }
+
+    /// Change the baud rate of the UART port we're connected to.
+    ///
+    /// The device switches baud rate as soon as it has applied the
+    /// new configuration, so we don't wait for its acknowledgement:
+    /// instead `reconfigure_host` is called with the device interface and
+    /// the new baud rate, and must switch the host UART to match.
+    /// We then verify that the device answers at the new baud rate.
+    /// Returns `Error::Unsupported` if we're not connected to a UART port.
+    pub fn set_baud_rate<F>(
+        &mut self,
+        baud_rate: u32,
+        delay_source: &mut impl DelayUs<u32>,
+        reconfigure_host: F,
+    ) -> Result<(), DI::InterfaceError>
+    where
+        F: FnOnce(&mut DI, u32),
+    {
+        if !matches!(self.di.port_id(), PortId::Uart1 | PortId::Uart2) {
+            return Err(Error::Unsupported);
+        }
+        let mut port_cfg: CfgPrt = self.poll(delay_source)?;
+        port_cfg.baud_rate = baud_rate;
+        self.send_command(&port_cfg)?;
+        // give the device time to finish sending at the old baud rate
+        delay_source.delay_us(BAUD_RATE_SWITCH_DELAY_US);
+
+        reconfigure_host(&mut self.di, baud_rate);
+        // discard any partial message received at the old baud rate
+        self.parser.reset();
+
+        let port_cfg: CfgPrt = self.poll(delay_source)?;
+        if port_cfg.baud_rate == baud_rate {
+            Ok(())
+        } else {
+            Err(Error::Unresponsive)
+        }
+    }
+
+    /// Reset the receiver, eg `CfgRst::cold_start` to measure time to
+    /// first fix, or to restart a receiver that has stopped responding.
+    /// The device doesn't acknowledge resets, so after waiting for it to
+    /// restart we discard any partial message and the messages received
+    /// before the reset.
+    /// A hardware reset reloads the configuration from non-volatile storage,
+    /// so `setup` may need to be called again (see `save_config`),
+    /// and power save mode must be entered again if required.
+    pub fn reset_receiver(
+        &mut self,
+        rst: &CfgRst,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<(), DI::InterfaceError> {
+        self.wake_if_power_save(delay_source)?;
+        self.send_command(rst)?;
+        delay_source.delay_us(RESET_SETTLE_DELAY_US);
+        self.discard_input()?;
+
+        self.last_nav_pvt = None;
+        self.last_mon_hw = None;
+        self.last_nav_dop = None;
+        self.last_nav_status = None;
+        self.last_tim_tp = None;
+        self.last_tim_tm2 = None;
+        self.ack_pending = None;
+        self.ack_result = None;
+        self.power_save = false;
+        self.asleep = false;
+        Ok(())
+    }
+
+    /// Detect the baud rate the device is using on the UART we're
+    /// connected to, by trying each of the `candidates` in turn.
+    ///
+    /// `reconfigure_host` is called with the device interface and each
+    /// candidate baud rate, and must switch the host UART to match.
+    /// We then poll UBX-CFG-PRT at that baud rate: it's detected when a
+    /// valid UBX message (sync bytes and checksum), such as the answer,
+    /// or the start of an NMEA sentence is received within `window_us`.
+    /// The host UART is left at the detected baud rate, which is returned,
+    /// or `Error::Unresponsive` if none of the candidates matched.
+    pub fn detect_baud_rate<F>(
+        &mut self,
+        candidates: &[u32],
+        window_us: u32,
+        delay_source: &mut impl DelayUs<u32>,
+        mut reconfigure_host: F,
+    ) -> Result<u32, DI::InterfaceError>
+    where
+        F: FnMut(&mut DI, u32),
+    {
+        for baud_rate in candidates {
+            reconfigure_host(&mut self.di, *baud_rate);
+            if self.listen_for_valid_message(window_us, delay_source)? {
+                return Ok(*baud_rate);
+            }
+        }
+        Err(Error::Unresponsive)
+    }
+
+    /// Poll the port configuration, then listen for a valid UBX message
+    /// or NMEA sentence prefix.
+    /// Returns true if one was received within `window_us`.
+    /// Each pass only reads what has already been received,
+    /// then waits for one poll interval, which bounds the window.
+    fn listen_for_valid_message(
+        &mut self,
+        window_us: u32,
+        delay_source: &mut impl DelayUs<u32>,
+    ) -> Result<bool, DI::InterfaceError> {
+        // discard anything received at the previous baud rate
+        self.discard_input()?;
+        // a device with periodic output disabled only answers requests
+        self.send_ubx_message(UBX_MSG_ID_CFG_PRT, &[])?;
+
+        let mut nmea_idx = 0;
+        let mut elapsed = 0;
+        while elapsed < window_us {
+            let available = self.di.fill();
+            for _ in 0..available {
+                let byte = self.di.read()?;
+                let ubx_found = self.parser.push(byte).is_some();
+                nmea_idx = nmea_prefix_step(nmea_idx, byte);
+                if ubx_found || nmea_idx == NMEA_PREFIX_LEN {
+                    self.parser.reset();
+                    return Ok(true);
+                }
+            }
+            delay_source.delay_us(RESPONSE_POLL_INTERVAL_US);
+            elapsed += RESPONSE_POLL_INTERVAL_US;
+        }
+        Ok(false)
+    }
+
+    /// Discard any bytes already received and any partial message,
+    /// to resynchronize with the device.
+    /// This doesn't wait for more bytes to arrive.
+    fn discard_input(&mut self) -> Result<(), DI::InterfaceError> {
+        let stale = self.di.fill();
+        for _ in 0..stale {
+            self.di.read()?;
+        }
+        self.parser.reset();
         Ok(())
     }
 
+    /// Record the answer to the command we're waiting on, if this is it
+    fn handle_ack(&mut self, ack: Option<AckM8>) {
+        if let Some(ack) = ack {
+            if self.ack_pending == Some(ack.msg_unique_id()) {
+                self.ack_result = Some(ack.acknowledged);
+            }
+        }
+    }
+
+    /// Handle a complete, checksum-verified frame from the parser
+    fn handle_frame(&mut self, header: UbxFrameHeader) {
+        let payload = self.parser.payload();
+        match header.msg_unique_id() {
+            UBX_MSG_ID_NAV_PVT => {
+                self.last_nav_pvt = messages::nav_pvt_from_bytes(payload);
+            }
+            UBX_MSG_ID_NAV_DOP => {
+                self.last_nav_dop = messages::nav_dop_from_bytes(payload);
+            }
+            UBX_MSG_ID_MON_HW => {
+                self.last_mon_hw = messages::mon_hw_from_bytes(payload);
+            }
+            UBX_MSG_ID_NAV_STATUS => {
+                self.last_nav_status = messages::nav_status_from_bytes(payload);
+            }
+            UBX_MSG_ID_TIM_TP => {
+                self.last_tim_tp = messages::tim_tp_from_bytes(payload);
+            }
+            UBX_MSG_ID_TIM_TM2 => {
+                self.last_tim_tm2 = messages::tim_tm2_from_bytes(payload);
+            }
+            UBX_MSG_ID_ACK_ACK => {
+                self.handle_ack(messages::ack_from_bytes(true, payload));
+            }
+            UBX_MSG_ID_ACK_NAK => {
+                self.handle_ack(messages::ack_from_bytes(false, payload));
+            }
+            _ => {
+                // unhandled message type: the parser has already consumed it
+            }
+        }
+    }
+
     pub fn handle_all_messages(
         &mut self,
         delay_source: &mut impl DelayUs<u32>,
@@ -197,62 +1020,517 @@ where
             } else {
                 break;
             }
-            delay_source.delay_us(1000);
+            delay_source.delay_us(MESSAGE_HANDLE_DELAY_US);
         }
         Ok(msg_count)
     }
 
-    /// return 1 if we handled a message?
+    /// Read available bytes from the device until a complete message
+    /// has been received and handled.
+    /// Returns 1 if we handled a message, or 0 if no complete message
+    /// was available yet. Partial messages are kept until the next call.
+    /// While the device is asleep (see `request_sleep`) this returns 0
+    /// without reading from the device.
     pub fn handle_one_message(&mut self) -> Result<usize, DI::InterfaceError> {
-        let mut msg_idx = 0;
+        self.handle_one_message_with(|_, _| {})
+    }
+
+    /// Like `handle_one_message`, but also passes the frame's header and
+    /// payload to `on_frame`: use this for messages the driver doesn't
+    /// keep, such as UBX-NAV-SAT (decode it with `NavSatM8::from_payload`).
+    pub fn handle_one_message_with<F>(
+        &mut self,
+        mut on_frame: F,
+    ) -> Result<usize, DI::InterfaceError>
+    where
+        F: FnMut(UbxFrameHeader, &[u8]),
+    {
+        if self.asleep {
+            return Ok(0);
+        }
+        match self.handle_one_frame()? {
+            Some(header) => {
+                on_frame(header, self.parser.payload());
+                Ok(1)
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Read available bytes from the device until a complete frame
+    /// has been received and handled.
+    /// Returns the header of the frame, whose payload remains available
+    /// from the parser until we read more bytes.
+    fn handle_one_frame(
+        &mut self,
+    ) -> Result<Option<UbxFrameHeader>, DI::InterfaceError> {
         // fill our incoming message buffer to avoid overruns
         let available = self.di.fill();
-        if available < UBX_MIN_MSG_LEN {
-            return Ok(0);
+        for _ in 0..available {
+            let byte = self.di.read()?;
+            if let Some(header) = self.parser.push(byte) {
+                self.handle_frame(header);
+                return Ok(Some(header));
+            }
         }
+        Ok(None)
+    }
+}
 
-        loop {
-            if msg_idx < 2 {
-                let byte = self.di.read()?;
-                if byte == UBX_PRELUDE_BYTES[msg_idx] {
-                    msg_idx += 1;
-                } else {
-                    // reset: the byte doesn't match the prelude sequence
-                    msg_idx = 0;
-                }
+/// Track progress through an NMEA sentence prefix such as "$GPGGA,":
+/// a '$', five upper case letters or digits, then a ','.
+/// Returns the number of prefix bytes matched so far, including `byte`.
+fn nmea_prefix_step(matched: usize, byte: u8) -> usize {
+    match matched {
+        _ if byte == b'$' => 1,
+        1..=5 if byte.is_ascii_uppercase() || byte.is_ascii_digit() => {
+            matched + 1
+        }
+        6 if byte == b',' => NMEA_PREFIX_LEN,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+
+    /// A UART that sends the bytes in `rx`, then nothing more
+    struct MockUart {
+        rx: [u8; 64],
+        rx_len: usize,
+        rx_idx: usize,
+    }
+
+    impl MockUart {
+        fn new(rx: &[u8]) -> Self {
+            let mut uart = Self {
+                rx: [0; 64],
+                rx_len: rx.len(),
+                rx_idx: 0,
+            };
+            uart.rx[..rx.len()].copy_from_slice(rx);
+            uart
+        }
+    }
+
+    impl hal::serial::Read<u8> for MockUart {
+        type Error = ();
+
+        fn read(&mut self) -> nb::Result<u8, ()> {
+            if self.rx_idx < self.rx_len {
+                self.rx_idx += 1;
+                Ok(self.rx[self.rx_idx - 1])
             } else {
-                let rc =
-                    self.di.read_many(&mut self.read_buf[..UBX_HEADER_LEN]);
-                let header_fail = match rc {
-                    Ok(read_count) => read_count != UBX_HEADER_LEN,
-                    _ => true,
-                };
-                if header_fail {
-                    return Ok(0);
+                Err(nb::Error::WouldBlock)
+            }
+        }
+    }
+
+    impl hal::serial::Write<u8> for MockUart {
+        type Error = ();
+
+        fn write(&mut self, _byte: u8) -> nb::Result<(), ()> {
+            Ok(())
+        }
+
+        fn flush(&mut self) -> nb::Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    struct NoDelay;
+
+    impl DelayUs<u32> for NoDelay {
+        fn delay_us(&mut self, _us: u32) {}
+    }
+
+    /// A delay that advances a simulated clock (microseconds)
+    struct ClockDelay<'a>(&'a Cell<u32>);
+
+    impl DelayUs<u32> for ClockDelay<'_> {
+        fn delay_us(&mut self, us: u32) {
+            self.0.set(self.0.get() + us);
+        }
+    }
+
+    /// A UART that sends the same UBX frame periodically,
+    /// following a simulated clock
+    struct PeriodicUart<'a> {
+        clock: &'a Cell<u32>,
+        period_us: u32,
+        next_at: u32,
+        /// time taken to receive each byte
+        byte_us: u32,
+        /// number of frames still to send
+        frames_left: usize,
+        frame: [u8; 64],
+        frame_len: usize,
+        frame_idx: usize,
+    }
+
+    impl<'a> PeriodicUart<'a> {
+        fn new(clock: &'a Cell<u32>, period_us: u32, frame: &[u8]) -> Self {
+            let mut uart = Self {
+                clock,
+                period_us,
+                next_at: 0,
+                byte_us: 0,
+                frames_left: usize::MAX,
+                frame: [0; 64],
+                frame_len: frame.len(),
+                frame_idx: frame.len(),
+            };
+            uart.frame[..frame.len()].copy_from_slice(frame);
+            uart
+        }
+    }
+
+    impl hal::serial::Read<u8> for PeriodicUart<'_> {
+        type Error = ();
+
+        fn read(&mut self) -> nb::Result<u8, ()> {
+            if self.frame_idx == self.frame_len
+                && self.frames_left > 0
+                && self.clock.get() >= self.next_at
+            {
+                self.frame_idx = 0;
+                self.frames_left -= 1;
+                self.next_at += self.period_us;
+            }
+            if self.frame_idx < self.frame_len {
+                self.clock.set(self.clock.get() + self.byte_us);
+                self.frame_idx += 1;
+                Ok(self.frame[self.frame_idx - 1])
+            } else {
+                Err(nb::Error::WouldBlock)
+            }
+        }
+    }
+
+    impl hal::serial::Write<u8> for PeriodicUart<'_> {
+        type Error = ();
+
+        fn write(&mut self, _byte: u8) -> nb::Result<(), ()> {
+            Ok(())
+        }
+
+        fn flush(&mut self) -> nb::Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    /// A UART that sends UBX-NAV-DOP every `period_us`
+    fn uart_sending_nav_dop(
+        clock: &Cell<u32>,
+        period_us: u32,
+    ) -> PeriodicUart<'_> {
+        let mut frame = [0u8; 64];
+        let frame_len =
+            encode_ubx_frame(0x01, 0x04, &[0u8; 18], &mut frame).unwrap();
+        PeriodicUart::new(clock, period_us, &frame[..frame_len])
+    }
+
+    /// A UART that answers with a single UBX frame, then goes quiet
+    fn uart_answering(class: u8, id: u8, payload: &[u8]) -> MockUart {
+        let mut frame = [0u8; 64];
+        let frame_len =
+            encode_ubx_frame(class, id, payload, &mut frame).unwrap();
+        MockUart::new(&frame[..frame_len])
+    }
+
+    /// UBX-CFG-RATE payload for 5Hz navigation
+    const CFG_RATE: [u8; 6] = [0xC8, 0x00, 0x01, 0x00, 0x01, 0x00];
+
+    #[test]
+    fn nav_sat_is_passed_to_frame_callback() {
+        // two satellites, the first used for navigation
+        let mut sat = [0u8; 32];
+        sat[5] = 2;
+        sat[8..10].copy_from_slice(&[0x00, 0x05]);
+        sat[16] = 0x08;
+        sat[20..22].copy_from_slice(&[0x06, 0x41]);
+        let uart = uart_answering(0x01, 0x35, &sat);
+        let mut driver = new_serial_driver(uart);
+        let mut used = [0u8; 2];
+        let mut used_count = 0;
+        let handled = driver.handle_one_message_with(|header, payload| {
+            if header.msg_unique_id() == UBX_MSG_ID_NAV_SAT {
+                let nav_sat = NavSatM8::from_payload(payload).unwrap();
+                for sv in nav_sat.svs().iter().filter(|sv| sv.sv_used()) {
+                    used[used_count] = sv.sv_id;
+                    used_count += 1;
                 }
+            }
+        });
+        assert_eq!(handled.unwrap(), 1);
+        assert_eq!(&used[..used_count], &[5]);
+    }
+
+    #[test]
+    fn ack_wait_times_out_on_silent_uart() {
+        let mut driver = new_serial_driver(MockUart::new(&[]));
+        let rc = driver.send_and_wait_ack(
+            UBX_MSG_ID_CFG_RATE,
+            &CFG_RATE,
+            &mut NoDelay,
+        );
+        assert!(matches!(rc, Err(Error::Unresponsive)));
+    }
+
+    #[test]
+    fn ack_wait_returns_after_ack_then_silence() {
+        let uart = uart_answering(0x05, 0x01, &[0x06, 0x08]);
+        let mut driver = new_serial_driver(uart);
+        let rc = driver.send_and_wait_ack(
+            UBX_MSG_ID_CFG_RATE,
+            &CFG_RATE,
+            &mut NoDelay,
+        );
+        assert!(rc.is_ok());
+    }
+
+    #[test]
+    fn ack_wait_reports_nak() {
+        let uart = uart_answering(0x05, 0x00, &[0x06, 0x08]);
+        let mut driver = new_serial_driver(uart);
+        let rc = driver.send_and_wait_ack(
+            UBX_MSG_ID_CFG_RATE,
+            &CFG_RATE,
+            &mut NoDelay,
+        );
+        assert!(matches!(rc, Err(Error::Nak)));
+    }
+
+    #[test]
+    fn ack_wait_times_out_despite_other_messages() {
+        let clock = Cell::new(0);
+        let uart = uart_sending_nav_dop(&clock, 1500);
+        let mut driver = new_serial_driver(uart);
+        driver.set_ack_timeout_us(100_000);
+        driver.set_ack_retries(0);
+        let rc = driver.send_and_wait_ack(
+            UBX_MSG_ID_CFG_RATE,
+            &CFG_RATE,
+            &mut ClockDelay(&clock),
+        );
+        assert!(matches!(rc, Err(Error::Unresponsive)));
+        assert!(clock.get() <= 100_000 + 2 * RESPONSE_POLL_INTERVAL_US);
+    }
+
+    #[test]
+    fn poll_skips_other_messages_until_the_answer() {
+        let mut rx = [0u8; 64];
+        let ack_len =
+            encode_ubx_frame(0x05, 0x01, &[0x06, 0x08], &mut rx).unwrap();
+        let dop = [
+            0x10, 0x00, 0x00, 0x00, 0x01, 0x01, 0x02, 0x01, 0x03, 0x01, 0x04,
+            0x01, 0x05, 0x01, 0x06, 0x01, 0x07, 0x01,
+        ];
+        let dop_len =
+            encode_ubx_frame(0x01, 0x04, &dop, &mut rx[ack_len..]).unwrap();
+        let uart = MockUart::new(&rx[..ack_len + dop_len]);
+        let mut driver = new_serial_driver(uart);
+        let dop = driver.poll::<NavDopM8>(&mut NoDelay).unwrap();
+        assert_eq!(dop.itow, 16);
+        assert_eq!(dop.g_dop, 0x0101);
+    }
+
+    #[test]
+    fn poll_times_out_on_silent_uart() {
+        let mut driver = new_serial_driver(MockUart::new(&[]));
+        let rc = driver.poll::<NavDopM8>(&mut NoDelay);
+        assert!(matches!(rc, Err(Error::Unresponsive)));
+    }
+
+    #[test]
+    fn poll_times_out_despite_other_messages() {
+        let clock = Cell::new(0);
+        // back-to-back frames for twice as long as the timeout
+        let mut uart = uart_sending_nav_dop(&clock, 500);
+        uart.byte_us = 20;
+        uart.frames_left = 400;
+        let mut driver = new_serial_driver(uart);
+        driver.set_poll_timeout_us(100_000);
+        let rc = driver.poll::<MonHardwareM8>(&mut ClockDelay(&clock));
+        assert!(matches!(rc, Err(Error::Unresponsive)));
+        assert!(clock.get() <= 100_000 + RESPONSE_POLL_INTERVAL_US);
+    }
+
+    #[test]
+    fn setup_reports_silent_uart_as_unresponsive() {
+        let mut driver = new_serial_driver(MockUart::new(&[]));
+        let rc = driver.setup(&mut NoDelay);
+        assert!(matches!(rc, Err(Error::Unresponsive)));
+    }
+
+    #[test]
+    fn setup_times_out_once_output_stops() {
+        // a factory default module answers the port poll and accepts the
+        // UBX-only output configuration, then sends nothing more
+        let mut prt = [0u8; UBX_MSG_LEN_CFG_PRT];
+        prt[0] = PortId::Uart1 as u8;
+        let mut rx = [0u8; 64];
+        let mut rx_len = 0;
+        rx_len += encode_ubx_frame(0x06, 0x00, &prt, &mut rx).unwrap();
+        rx_len +=
+            encode_ubx_frame(0x05, 0x01, &[0x06, 0x00], &mut rx[rx_len..])
+                .unwrap();
+        let mut driver = new_serial_driver(MockUart::new(&rx[..rx_len]));
+        let rc = driver.setup(&mut NoDelay);
+        assert!(matches!(rc, Err(Error::Unresponsive)));
+    }
+
+    #[test]
+    fn setup_falls_back_to_valset_when_port_poll_is_rejected() {
+        let mut rx = [0u8; 64];
+        let mut rx_len = 0;
+        rx_len += encode_ubx_frame(0x05, 0x00, &[0x06, 0x00], &mut rx).unwrap();
+        rx_len +=
+            encode_ubx_frame(0x05, 0x01, &[0x06, 0x8A], &mut rx[rx_len..])
+                .unwrap();
+        let mut driver = new_serial_driver(MockUart::new(&rx[..rx_len]));
+        assert!(driver.setup(&mut NoDelay).is_ok());
+    }
+
+    #[test]
+    fn setup_reports_rejected_legacy_config() {
+        // the port poll is answered, but its configuration is rejected
+        let mut prt = [0u8; UBX_MSG_LEN_CFG_PRT];
+        prt[0] = PortId::Uart1 as u8;
+        let mut rx = [0u8; 64];
+        let mut rx_len = 0;
+        rx_len += encode_ubx_frame(0x06, 0x00, &prt, &mut rx).unwrap();
+        rx_len +=
+            encode_ubx_frame(0x05, 0x00, &[0x06, 0x00], &mut rx[rx_len..])
+                .unwrap();
+        rx_len +=
+            encode_ubx_frame(0x05, 0x01, &[0x06, 0x8A], &mut rx[rx_len..])
+                .unwrap();
+        let mut driver = new_serial_driver(MockUart::new(&rx[..rx_len]));
+        let rc = driver.setup(&mut NoDelay);
+        assert!(matches!(rc, Err(Error::Nak)));
+    }
 
-                let msg_unique_id: u16 =
-                    (self.read_buf[0] as u16) << 8 | (self.read_buf[1] as u16);
-                return match msg_unique_id {
-                    UBX_MSG_ID_NAV_PVT => {
-                        self.handle_msg_nav_pvt()?;
-                        Ok(1)
-                    }
-                    UBX_MSG_ID_NAV_DOP => {
-                        self.handle_msg_nav_dop()?;
-                        Ok(1)
-                    }
-                    UBX_MSG_ID_MON_HW => {
-                        self.handle_msg_mon_hw()?;
-                        Ok(1)
-                    }
-                    _ => {
-                        // unhandled message type...skip to next message
-                        self.skip_unhandled_msg()?;
-                        Ok(1)
-                    }
-                };
+    #[test]
+    fn baud_rate_detection_times_out_on_silent_uart() {
+        let mut driver = new_serial_driver(MockUart::new(&[]));
+        let rc = driver.detect_baud_rate(
+            &[9600, 38400],
+            DEFAULT_AUTOBAUD_WINDOW_US,
+            &mut NoDelay,
+            |_, _| {},
+        );
+        assert!(matches!(rc, Err(Error::Unresponsive)));
+    }
+
+    #[test]
+    fn baud_rate_change_is_unsupported_over_usb() {
+        let iface =
+            SerialInterface::new_with_port(MockUart::new(&[]), PortId::Usb);
+        let mut driver: UbxDriver<_> = UbxDriver::new_with_interface(iface);
+        let mut reconfigured = false;
+        let rc = driver.set_baud_rate(115_200, &mut NoDelay, |_, _| {
+            reconfigured = true;
+        });
+        assert!(matches!(rc, Err(Error::Unsupported)));
+        assert!(!reconfigured);
+    }
+
+    /// A UART connected to a device at `device_baud` that only answers
+    /// requests, with a UBX-CFG-PRT frame
+    struct RequestOnlyUart {
+        host_baud: u32,
+        device_baud: u32,
+        answer: MockUart,
+        requested: bool,
+    }
+
+    impl hal::serial::Read<u8> for RequestOnlyUart {
+        type Error = ();
+
+        fn read(&mut self) -> nb::Result<u8, ()> {
+            if self.requested {
+                self.answer.read()
+            } else {
+                Err(nb::Error::WouldBlock)
             }
         }
     }
+
+    impl hal::serial::Write<u8> for RequestOnlyUart {
+        type Error = ();
+
+        fn write(&mut self, _byte: u8) -> nb::Result<(), ()> {
+            if self.host_baud == self.device_baud {
+                self.requested = true;
+            }
+            Ok(())
+        }
+
+        fn flush(&mut self) -> nb::Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn baud_rate_detection_polls_a_quiet_device() {
+        let mut prt = [0u8; UBX_MSG_LEN_CFG_PRT];
+        prt[0] = PortId::Uart1 as u8;
+        let uart = RequestOnlyUart {
+            host_baud: 0,
+            device_baud: 38400,
+            answer: uart_answering(0x06, 0x00, &prt),
+            requested: false,
+        };
+        let mut driver = new_serial_driver(uart);
+        let rc = driver.detect_baud_rate(
+            &[9600, 38400, 115_200],
+            DEFAULT_AUTOBAUD_WINDOW_US,
+            &mut NoDelay,
+            |di, baud_rate| di.serial_mut().host_baud = baud_rate,
+        );
+        assert_eq!(rc.unwrap(), 38400);
+    }
+
+    #[test]
+    fn sleep_and_wake_on_silent_uart() {
+        let mut driver = new_serial_driver(MockUart::new(&[]));
+        driver
+            .request_sleep(&RxmPmReqM8::backup(1000, 0), &mut NoDelay)
+            .unwrap();
+        assert!(driver.is_asleep());
+        // a new request wakes the device first, without waiting for input
+        driver
+            .request_sleep(&RxmPmReqM8::backup(1000, 0), &mut NoDelay)
+            .unwrap();
+        driver.wake_from_sleep(&mut NoDelay).unwrap();
+        assert!(!driver.is_asleep());
+    }
+
+    #[test]
+    fn reset_wakes_sleeping_receiver_on_silent_uart() {
+        let mut driver = new_serial_driver(MockUart::new(&[]));
+        driver
+            .request_sleep(&RxmPmReqM8::backup(1000, 0), &mut NoDelay)
+            .unwrap();
+        driver
+            .reset_receiver(
+                &CfgRst::hot_start(ResetMode::ControlledGnssStop),
+                &mut NoDelay,
+            )
+            .unwrap();
+        assert!(!driver.is_asleep());
+        assert!(!driver.is_power_save());
+    }
+
+    #[test]
+    fn poll_reports_answer_longer_than_buffer() {
+        let uart = uart_answering(0x0A, 0x04, &[0; UBX_MSG_LEN_MON_VER]);
+        let iface = SerialInterface::new(uart);
+        let mut driver = UbxDriver::<_, 32>::new_with_interface(iface);
+        let rc = driver.poll::<MonVersionM8>(&mut NoDelay);
+        assert!(matches!(rc, Err(Error::MessageTooLong)));
+    }
 }
@@ -4,19 +4,136 @@ pub const UBX_CKSUM_LEN: usize = 2;
 
 pub const UBX_WRAPPER_LEN: usize =
     UBX_PRELUDE_LEN + UBX_HEADER_LEN + UBX_CKSUM_LEN;
-pub const UBX_MIN_MSG_LEN: usize = UBX_MSG_LEN_NAV_DOP + UBX_WRAPPER_LEN;
-#[allow(unused)]
-pub const UBX_MAX_MSG_LEN: usize = UBX_MSG_LEN_NAV_PVT + UBX_WRAPPER_LEN;
 
 pub const UBX_PRELUDE_BYTES: [u8; 2] = [0xB5, 0x62];
 
+/// Generate the 16 bit (8-bit Fletcher) checksum for a UBX message.
+/// The checksum covers the class, id, length and payload fields,
+/// but not the prelude (sync) bytes.
+pub fn ubx_checksum(data: &[u8]) -> [u8; UBX_CKSUM_LEN] {
+    let mut checksum = [0u8; UBX_CKSUM_LEN];
+    for word in data {
+        checksum[0] = checksum[0].wrapping_add(*word);
+        checksum[1] = checksum[1].wrapping_add(checksum[0]);
+    }
+    checksum
+}
+
+/// A UBX message type that can be decoded from a received payload.
+/// Sending a message's class and ID with an empty payload polls
+/// the device for the current value of that message.
+pub trait UbxMessage: Sized {
+    /// Combined class and message ID, eg 0x0107 for UBX-NAV-PVT
+    const MSG_UNIQUE_ID: u16;
+
+    /// Decode the message from its payload, or `None` if it's malformed
+    fn from_payload(buf: &[u8]) -> Option<Self>;
+}
+
+/// A UBX message that can be encoded and sent to the device,
+/// such as a UBX-CFG configuration message
+pub trait UbxCommand {
+    /// Combined class and message ID, eg 0x0601 for UBX-CFG-MSG
+    const MSG_UNIQUE_ID: u16;
+
+    /// Encode the message payload into `buf`.
+    /// Returns the length of the payload, or `None` if `buf` is too small.
+    fn write_payload(&self, buf: &mut [u8]) -> Option<usize>;
+}
+
+/// Read a little-endian u16 from `buf` at byte offset `idx`
+pub(crate) fn read_u16(buf: &[u8], idx: usize) -> u16 {
+    u16::from_le_bytes([buf[idx], buf[idx + 1]])
+}
+
+/// Read a little-endian i16 from `buf` at byte offset `idx`
+pub(crate) fn read_i16(buf: &[u8], idx: usize) -> i16 {
+    i16::from_le_bytes([buf[idx], buf[idx + 1]])
+}
+
+/// Read a little-endian u32 from `buf` at byte offset `idx`
+pub(crate) fn read_u32(buf: &[u8], idx: usize) -> u32 {
+    u32::from_le_bytes([buf[idx], buf[idx + 1], buf[idx + 2], buf[idx + 3]])
+}
+
+/// Read a little-endian i32 from `buf` at byte offset `idx`
+pub(crate) fn read_i32(buf: &[u8], idx: usize) -> i32 {
+    i32::from_le_bytes([buf[idx], buf[idx + 1], buf[idx + 2], buf[idx + 3]])
+}
+
+/// Write a little-endian u16 into `buf` at byte offset `idx`
+pub(crate) fn write_u16(buf: &mut [u8], idx: usize, val: u16) {
+    buf[idx..idx + 2].copy_from_slice(&val.to_le_bytes());
+}
+
+/// Write a little-endian u32 into `buf` at byte offset `idx`
+pub(crate) fn write_u32(buf: &mut [u8], idx: usize, val: u32) {
+    buf[idx..idx + 4].copy_from_slice(&val.to_le_bytes());
+}
+
+/// Encode a complete UBX frame (prelude, class, id, length, payload,
+/// checksum) into `out`, ready for sending to the device.
+/// Returns the length of the encoded frame, or `None` if `out` is
+/// too small to hold it.
+pub fn encode_ubx_frame(
+    class: u8,
+    id: u8,
+    payload: &[u8],
+    out: &mut [u8],
+) -> Option<usize> {
+    let pay_len = u16::try_from(payload.len()).ok()?;
+    let min_pay_idx = UBX_PRELUDE_LEN + UBX_HEADER_LEN;
+    let max_pay_idx = min_pay_idx + payload.len();
+    let max_msg_idx = max_pay_idx + UBX_CKSUM_LEN;
+    if out.len() < max_msg_idx {
+        return None;
+    }
+    out[..UBX_PRELUDE_LEN].copy_from_slice(&UBX_PRELUDE_BYTES);
+    out[2] = class;
+    out[3] = id;
+    // The length is that of the payload only, as a little-endian u16
+    out[4..min_pay_idx].copy_from_slice(&pay_len.to_le_bytes());
+    out[min_pay_idx..max_pay_idx].copy_from_slice(payload);
+    let checksum = ubx_checksum(&out[UBX_PRELUDE_LEN..max_pay_idx]);
+    out[max_pay_idx..max_msg_idx].copy_from_slice(&checksum);
+    Some(max_msg_idx)
+}
+
 pub const UBX_MSG_ID_NAV_PVT: u16 = 0x0107;
+pub const UBX_MSG_ID_NAV_STATUS: u16 = 0x0103;
 pub const UBX_MSG_ID_NAV_DOP: u16 = 0x0104;
+pub const UBX_MSG_ID_NAV_SAT: u16 = 0x0135;
+pub const UBX_MSG_ID_MON_VER: u16 = 0x0A04;
 pub const UBX_MSG_ID_MON_HW: u16 = 0x0A09;
+pub const UBX_MSG_ID_RXM_PMREQ: u16 = 0x0241;
+pub const UBX_MSG_ID_ACK_NAK: u16 = 0x0500;
+pub const UBX_MSG_ID_ACK_ACK: u16 = 0x0501;
+pub const UBX_MSG_ID_CFG_PRT: u16 = 0x0600;
+pub const UBX_MSG_ID_CFG_MSG: u16 = 0x0601;
+pub const UBX_MSG_ID_CFG_RST: u16 = 0x0604;
+pub const UBX_MSG_ID_CFG_RATE: u16 = 0x0608;
+pub const UBX_MSG_ID_CFG_CFG: u16 = 0x0609;
+pub const UBX_MSG_ID_CFG_RXM: u16 = 0x0611;
+pub const UBX_MSG_ID_CFG_NAV5: u16 = 0x0624;
+pub const UBX_MSG_ID_CFG_TP5: u16 = 0x0631;
+pub const UBX_MSG_ID_CFG_PM2: u16 = 0x063B;
+pub const UBX_MSG_ID_CFG_GNSS: u16 = 0x063E;
+pub const UBX_MSG_ID_CFG_PMS: u16 = 0x0686;
+pub const UBX_MSG_ID_CFG_VALSET: u16 = 0x068A;
+pub const UBX_MSG_ID_CFG_VALGET: u16 = 0x068B;
+pub const UBX_MSG_ID_CFG_VALDEL: u16 = 0x068C;
+pub const UBX_MSG_ID_TIM_TP: u16 = 0x0D01;
+pub const UBX_MSG_ID_TIM_TM2: u16 = 0x0D03;
+
+pub const NMEA_MSG_ID_GGA: u16 = 0xF000;
+pub const NMEA_MSG_ID_GLL: u16 = 0xF001;
+pub const NMEA_MSG_ID_GSA: u16 = 0xF002;
+pub const NMEA_MSG_ID_GSV: u16 = 0xF003;
+pub const NMEA_MSG_ID_RMC: u16 = 0xF004;
+pub const NMEA_MSG_ID_VTG: u16 = 0xF005;
 
 /// Support UBX-NAV-PVT message: Navigation Position Velocity Time Solution
 /// See 32.17.14 UBX-NAV-PVT (0x01 0x07)
-#[repr(C)]
 #[derive(Copy, Clone, Debug)]
 pub struct NavPosVelTimeM8 {
     /// GPS time of week of the navigation epoch. (ms)
@@ -89,12 +206,57 @@ pub struct NavPosVelTimeM8 {
 
 pub const UBX_MSG_LEN_NAV_PVT: usize = 92;
 pub fn nav_pvt_from_bytes(buf: &[u8]) -> Option<NavPosVelTimeM8> {
-    ubx_struct_from_bytes(buf)
+    if buf.len() < UBX_MSG_LEN_NAV_PVT {
+        return None;
+    }
+    let mut reserved1 = [0u8; 6];
+    reserved1.copy_from_slice(&buf[78..84]);
+    Some(NavPosVelTimeM8 {
+        itow: read_u32(buf, 0),
+        year: read_u16(buf, 4),
+        month: buf[6],
+        day: buf[7],
+        hour: buf[8],
+        min: buf[9],
+        sec: buf[10],
+        validity_flags: buf[11],
+        time_accuracy: read_u32(buf, 12),
+        nanosecond: read_i32(buf, 16),
+        fix_type: buf[20],
+        flags: buf[21],
+        flags2: buf[22],
+        num_satellites: buf[23],
+        lon: read_i32(buf, 24),
+        lat: read_i32(buf, 28),
+        height: read_i32(buf, 32),
+        height_msl: read_i32(buf, 36),
+        h_accuracy: read_u32(buf, 40),
+        v_accuracy: read_u32(buf, 44),
+        vel_north: read_i32(buf, 48),
+        vel_east: read_i32(buf, 52),
+        vel_down: read_i32(buf, 56),
+        ground_speed: read_i32(buf, 60),
+        heading_motion: read_i32(buf, 64),
+        speed_accuracy: read_u32(buf, 68),
+        heading_accuracy: read_u32(buf, 72),
+        pos_dop: read_u16(buf, 76),
+        reserved1,
+        heading_vehicle: read_i32(buf, 84),
+        mag_dec: read_i16(buf, 88),
+        mag_accuracy: read_u16(buf, 90),
+    })
+}
+
+impl UbxMessage for NavPosVelTimeM8 {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_NAV_PVT;
+
+    fn from_payload(buf: &[u8]) -> Option<Self> {
+        nav_pvt_from_bytes(buf)
+    }
 }
 
 /// UBX-MON-HW message: Hardware Status
 /// See 32.16.4 UBX-MON-HW (0x0A 0x09)
-#[repr(C)]
 #[derive(Copy, Clone, Debug)]
 pub struct MonHardwareM8 {
     pub pin_sel: u32, //0 pinSel - Mask of Pins Set as Peripheral/PIO
@@ -120,12 +282,123 @@ pub struct MonHardwareM8 {
 
 pub const UBX_MSG_LEN_MON_HW: usize = 60;
 pub fn mon_hw_from_bytes(buf: &[u8]) -> Option<MonHardwareM8> {
-    ubx_struct_from_bytes(buf)
+    if buf.len() < UBX_MSG_LEN_MON_HW {
+        return None;
+    }
+    let mut pin_maps = [0u8; 17];
+    pin_maps.copy_from_slice(&buf[28..45]);
+    Some(MonHardwareM8 {
+        pin_sel: read_u32(buf, 0),
+        pin_bank: read_u32(buf, 4),
+        pin_direction: read_u32(buf, 8),
+        pin_values: read_u32(buf, 12),
+        noise_per_ms: read_u16(buf, 16),
+        agc_count: read_u16(buf, 18),
+        ant_status: buf[20],
+        ant_power: buf[21],
+        flags: buf[22],
+        reserved: buf[23],
+        used_mask: read_u32(buf, 24),
+        pin_maps,
+        jam_ind: buf[45],
+        reserved2: [buf[46], buf[47]],
+        pin_irq: read_u32(buf, 48),
+        pull_high: read_u32(buf, 52),
+        pull_low: read_u32(buf, 56),
+    })
+}
+
+impl UbxMessage for MonHardwareM8 {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_MON_HW;
+
+    fn from_payload(buf: &[u8]) -> Option<Self> {
+        mon_hw_from_bytes(buf)
+    }
+}
+
+/// Maximum number of extension strings we keep from a UBX-MON-VER message
+pub const UBX_MON_VER_MAX_EXTENSIONS: usize = 8;
+
+/// UBX-MON-VER message: Receiver/Software Version.
+/// See 32.16.18 UBX-MON-VER (0x0A 0x04)
+/// This is usually polled rather than sent periodically.
+/// Each extension needs 30 bytes, in addition to 40 bytes for the
+/// software and hardware versions.
+#[derive(Copy, Clone, Debug)]
+pub struct MonVersionM8 {
+    pub sw_version: [u8; 30], //0 swVersion - Nul-terminated software version string
+    pub hw_version: [u8; 10], //30 hwVersion - Nul-terminated hardware version string
+    /// 40 + 30*N extension - Extended software information strings
+    pub extensions: [[u8; 30]; UBX_MON_VER_MAX_EXTENSIONS],
+    /// Number of valid entries in `extensions`
+    pub extension_count: usize,
+}
+
+impl MonVersionM8 {
+    /// The software version string
+    pub fn sw_version(&self) -> &str {
+        str_from_nul_terminated(&self.sw_version)
+    }
+
+    /// The hardware version string
+    pub fn hw_version(&self) -> &str {
+        str_from_nul_terminated(&self.hw_version)
+    }
+
+    /// The extended software information string at `idx`, if any
+    pub fn extension(&self, idx: usize) -> Option<&str> {
+        if idx < self.extension_count {
+            Some(str_from_nul_terminated(&self.extensions[idx]))
+        } else {
+            None
+        }
+    }
+}
+
+/// Read a string from a fixed-length, nul-terminated field
+fn str_from_nul_terminated(buf: &[u8]) -> &str {
+    let len = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+    core::str::from_utf8(&buf[..len]).unwrap_or("")
+}
+
+/// Length of the fixed part of UBX-MON-VER
+pub const UBX_MSG_LEN_MON_VER: usize = 40;
+/// Length of each repeated extension string in UBX-MON-VER
+pub const UBX_MSG_LEN_MON_VER_EXT: usize = 30;
+pub fn mon_ver_from_bytes(buf: &[u8]) -> Option<MonVersionM8> {
+    if buf.len() < UBX_MSG_LEN_MON_VER {
+        return None;
+    }
+    let mut sw_version = [0u8; 30];
+    sw_version.copy_from_slice(&buf[0..30]);
+    let mut hw_version = [0u8; 10];
+    hw_version.copy_from_slice(&buf[30..40]);
+    let mut extensions = [[0u8; 30]; UBX_MON_VER_MAX_EXTENSIONS];
+    let extension_count = ((buf.len() - UBX_MSG_LEN_MON_VER)
+        / UBX_MSG_LEN_MON_VER_EXT)
+        .min(UBX_MON_VER_MAX_EXTENSIONS);
+    for (idx, ext) in extensions[..extension_count].iter_mut().enumerate() {
+        let start = UBX_MSG_LEN_MON_VER + idx * UBX_MSG_LEN_MON_VER_EXT;
+        ext.copy_from_slice(&buf[start..start + UBX_MSG_LEN_MON_VER_EXT]);
+    }
+    Some(MonVersionM8 {
+        sw_version,
+        hw_version,
+        extensions,
+        extension_count,
+    })
+}
+
+impl UbxMessage for MonVersionM8 {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_MON_VER;
+
+    fn from_payload(buf: &[u8]) -> Option<Self> {
+        mon_ver_from_bytes(buf)
+    }
 }
 
 /// UBX-NAV-DOP message: Dilution of precision
 /// See 32.17.5 UBX-NAV-DOP (0x01 0x04)
-#[repr(C)]
 #[derive(Copy, Clone, Debug)]
 pub struct NavDopM8 {
     pub itow: u32,  //0 ms GPS time of week of the navigation epoch.
@@ -140,22 +413,679 @@ pub struct NavDopM8 {
 
 pub const UBX_MSG_LEN_NAV_DOP: usize = 18;
 pub fn nav_dop_from_bytes(buf: &[u8]) -> Option<NavDopM8> {
-    ubx_struct_from_bytes(buf)
+    if buf.len() < UBX_MSG_LEN_NAV_DOP {
+        return None;
+    }
+    Some(NavDopM8 {
+        itow: read_u32(buf, 0),
+        g_dop: read_u16(buf, 4),
+        p_dop: read_u16(buf, 6),
+        t_dop: read_u16(buf, 8),
+        v_dop: read_u16(buf, 10),
+        h_dop: read_u16(buf, 12),
+        n_dop: read_u16(buf, 14),
+        e_dop: read_u16(buf, 16),
+    })
+}
+
+impl UbxMessage for NavDopM8 {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_NAV_DOP;
+
+    fn from_payload(buf: &[u8]) -> Option<Self> {
+        nav_dop_from_bytes(buf)
+    }
 }
 
-//TODO: add UBX-CFG-MSG and support for serializing it for sending
+/// UBX-NAV-STATUS message: Receiver Navigation Status
+/// See 32.17.21 UBX-NAV-STATUS (0x01 0x03)
+#[derive(Copy, Clone, Debug)]
+pub struct NavStatusM8 {
+    pub itow: u32, //0 ms GPS time of week of the navigation epoch.
+    /// 4 gpsFix - GNSS fix type:
+    /// 0 no fix, 1: dead reckoning only, 2: 2D-fix, 3: 3D-fix,
+    /// 4: GNSS + dead reckoning combined 5: time only fix
+    pub fix_type: u8,
+    pub flags: u8, //5 flags - Navigation Status Flags, see accessors below
+    pub fix_stat: u8, //6 fixStat - Fix Status Information, see accessors below
+    pub flags2: u8, //7 flags2 - Further information about navigation output
+    pub ttff: u32, //8 ttff - Time to first fix (millisecond time tag, ms)
+    pub msss: u32, //12 msss - Milliseconds since Startup / Reset (ms)
+}
+
+impl NavStatusM8 {
+    /// Position and velocity valid and within DOP and ACC Masks
+    pub fn gps_fix_ok(&self) -> bool {
+        self.flags & 0x01 != 0
+    }
+
+    /// Differential corrections were applied
+    pub fn diff_soln(&self) -> bool {
+        self.flags & 0x02 != 0
+    }
+
+    /// Valid GPS week number
+    pub fn week_set(&self) -> bool {
+        self.flags & 0x04 != 0
+    }
+
+    /// Valid GPS time of week
+    pub fn tow_set(&self) -> bool {
+        self.flags & 0x08 != 0
+    }
+
+    /// Differential corrections available
+    pub fn diff_corr(&self) -> bool {
+        self.fix_stat & 0x01 != 0
+    }
+
+    /// Valid carrier phase range solution
+    pub fn carr_soln_valid(&self) -> bool {
+        self.fix_stat & 0x02 != 0
+    }
+
+    /// Power save mode state:
+    /// 0 acquisition (or power save mode not enabled), 1 tracking,
+    /// 2 power optimized tracking, 3 inactive
+    pub fn psm_state(&self) -> u8 {
+        self.flags2 & 0x03
+    }
+
+    /// Spoofing detection state:
+    /// 0 unknown or deactivated, 1 no spoofing indicated,
+    /// 2 spoofing indicated, 3 multiple spoofing indications
+    pub fn spoof_det_state(&self) -> u8 {
+        (self.flags2 >> 3) & 0x03
+    }
+
+    /// Carrier phase range solution status:
+    /// 0 no carrier phase solution, 1 float solution, 2 fixed solution
+    pub fn carr_soln(&self) -> u8 {
+        (self.flags2 >> 6) & 0x03
+    }
+}
+
+pub const UBX_MSG_LEN_NAV_STATUS: usize = 16;
+pub fn nav_status_from_bytes(buf: &[u8]) -> Option<NavStatusM8> {
+    if buf.len() < UBX_MSG_LEN_NAV_STATUS {
+        return None;
+    }
+    Some(NavStatusM8 {
+        itow: read_u32(buf, 0),
+        fix_type: buf[4],
+        flags: buf[5],
+        fix_stat: buf[6],
+        flags2: buf[7],
+        ttff: read_u32(buf, 8),
+        msss: read_u32(buf, 12),
+    })
+}
+
+impl UbxMessage for NavStatusM8 {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_NAV_STATUS;
+
+    fn from_payload(buf: &[u8]) -> Option<Self> {
+        nav_status_from_bytes(buf)
+    }
+}
+
+/// Information about one satellite, from UBX-NAV-SAT
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NavSatSvInfoM8 {
+    pub gnss_id: u8, //0 gnssId - GNSS identifier (0=GPS, 1=SBAS, 2=Galileo, 3=BeiDou, 5=QZSS, 6=GLONASS)
+    pub sv_id: u8,   //1 svId - Satellite identifier
+    pub cno: u8,     //2 cno - Carrier to noise ratio (signal strength, dBHz)
+    pub elev: i8, //3 elev - Elevation (range: +/-90), unknown if out of range (deg)
+    pub azim: i16, //4 azim - Azimuth (range 0-360), unknown if elevation is out of range (deg)
+    pub pr_res: i16, //6 prRes - Pseudorange residual (0.1 m)
+    pub flags: u32, //8 flags - Bitmask, see accessors below
+}
+
+impl NavSatSvInfoM8 {
+    /// Signal quality indicator:
+    /// 0 no signal, 1 searching signal, 2 signal acquired,
+    /// 3 signal detected but unusable, 4 code locked and time synchronized,
+    /// 5, 6, 7 code and carrier locked and time synchronized
+    pub fn quality_ind(&self) -> u8 {
+        (self.flags & 0x07) as u8
+    }
+
+    /// Whether this satellite is currently being used for navigation
+    pub fn sv_used(&self) -> bool {
+        self.flags & 0x08 != 0
+    }
+
+    /// Signal health: 0 unknown, 1 healthy, 2 unhealthy
+    pub fn health(&self) -> u8 {
+        ((self.flags >> 4) & 0x03) as u8
+    }
+
+    /// Whether differential correction data is available for this satellite
+    pub fn diff_corr(&self) -> bool {
+        self.flags & 0x40 != 0
+    }
+}
+
+/// Maximum number of satellites we keep from a UBX-NAV-SAT message:
+/// the number of tracking channels on an M8 receiver
+pub const UBX_NAV_SAT_MAX_SVS: usize = 72;
+
+/// UBX-NAV-SAT message: Satellite Information
+/// See 32.17.20 UBX-NAV-SAT (0x01 0x35)
+/// This is large, so the driver doesn't keep the last one received:
+/// poll it, or decode it from `UbxDriver::handle_one_message_with`.
+#[derive(Copy, Clone, Debug)]
+pub struct NavSatM8 {
+    pub itow: u32,   //0 ms GPS time of week of the navigation epoch.
+    pub version: u8, //4 Message version (1 for this version)
+    /// 5 numSvs - Number of satellites reported by the device.
+    /// Only the first `UBX_NAV_SAT_MAX_SVS` of these are kept.
+    pub num_svs: u8,
+    /// Number of valid entries in `sv_info`
+    pub sv_count: usize,
+    /// Per-satellite information, starting at offset 8
+    pub sv_info: [NavSatSvInfoM8; UBX_NAV_SAT_MAX_SVS],
+}
 
-/// Read a UBX message type from bytes
-pub fn ubx_struct_from_bytes<T>(input: &[u8]) -> Option<T> {
-    unsafe {
-        // due to struct layout / padding issues, the size of the struct
-        // might be, say, 4-byte word aligned while the message length is smaller
-        let mut msg = core::mem::zeroed();
-        let msg_as_slice = core::slice::from_raw_parts_mut(
-            &mut msg as *mut T as *mut u8,
-            input.len(),
+impl NavSatM8 {
+    /// The valid per-satellite entries
+    pub fn svs(&self) -> &[NavSatSvInfoM8] {
+        &self.sv_info[..self.sv_count]
+    }
+}
+
+/// Length of the fixed part of UBX-NAV-SAT
+pub const UBX_MSG_LEN_NAV_SAT: usize = 8;
+/// Length of each repeated per-satellite block in UBX-NAV-SAT
+pub const UBX_MSG_LEN_NAV_SAT_SV: usize = 12;
+pub fn nav_sat_from_bytes(buf: &[u8]) -> Option<NavSatM8> {
+    if buf.len() < UBX_MSG_LEN_NAV_SAT {
+        return None;
+    }
+    let num_svs = buf[5];
+    let blocks_len = buf.len() - UBX_MSG_LEN_NAV_SAT;
+    if blocks_len < (num_svs as usize) * UBX_MSG_LEN_NAV_SAT_SV {
+        return None;
+    }
+    let sv_count = (num_svs as usize).min(UBX_NAV_SAT_MAX_SVS);
+    let mut sv_info = [NavSatSvInfoM8::default(); UBX_NAV_SAT_MAX_SVS];
+    for (idx, info) in sv_info[..sv_count].iter_mut().enumerate() {
+        let block = &buf[UBX_MSG_LEN_NAV_SAT + idx * UBX_MSG_LEN_NAV_SAT_SV..];
+        *info = NavSatSvInfoM8 {
+            gnss_id: block[0],
+            sv_id: block[1],
+            cno: block[2],
+            elev: block[3] as i8,
+            azim: read_i16(block, 4),
+            pr_res: read_i16(block, 6),
+            flags: read_u32(block, 8),
+        };
+    }
+    Some(NavSatM8 {
+        itow: read_u32(buf, 0),
+        version: buf[4],
+        num_svs,
+        sv_count,
+        sv_info,
+    })
+}
+
+impl UbxMessage for NavSatM8 {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_NAV_SAT;
+
+    fn from_payload(buf: &[u8]) -> Option<Self> {
+        nav_sat_from_bytes(buf)
+    }
+}
+
+/// UBX-ACK-ACK / UBX-ACK-NAK message: Message (not) acknowledged
+/// See 32.9 UBX-ACK (0x05)
+#[derive(Copy, Clone, Debug)]
+pub struct AckM8 {
+    /// true for UBX-ACK-ACK, false for UBX-ACK-NAK
+    pub acknowledged: bool,
+    pub class_id: u8, //0 clsID - Class ID of the (not) acknowledged message
+    pub msg_id: u8,   //1 msgID - Message ID of the (not) acknowledged message
+}
+
+impl AckM8 {
+    /// The combined class and message ID of the (not) acknowledged message
+    pub fn msg_unique_id(&self) -> u16 {
+        (self.class_id as u16) << 8 | (self.msg_id as u16)
+    }
+}
+
+pub const UBX_MSG_LEN_ACK: usize = 2;
+pub fn ack_from_bytes(acknowledged: bool, buf: &[u8]) -> Option<AckM8> {
+    if buf.len() < UBX_MSG_LEN_ACK {
+        return None;
+    }
+    Some(AckM8 {
+        acknowledged,
+        class_id: buf[0],
+        msg_id: buf[1],
+    })
+}
+
+/// UBX-RXM-PMREQ flags: enter backup mode
+pub const PMREQ_FLAGS_BACKUP: u32 = 0x0000_0002;
+/// UBX-RXM-PMREQ flags: force the request, even if the receiver is busy
+pub const PMREQ_FLAGS_FORCE: u32 = 0x0000_0004;
+
+/// UBX-RXM-PMREQ wakeupSources: wake on UART RX
+pub const PMREQ_WAKEUP_UART_RX: u32 = 0x0000_0008;
+/// UBX-RXM-PMREQ wakeupSources: wake on EXTINT0
+pub const PMREQ_WAKEUP_EXTINT0: u32 = 0x0000_0020;
+/// UBX-RXM-PMREQ wakeupSources: wake on EXTINT1
+pub const PMREQ_WAKEUP_EXTINT1: u32 = 0x0000_0040;
+/// UBX-RXM-PMREQ wakeupSources: wake on SPI chip select
+pub const PMREQ_WAKEUP_SPI_CS: u32 = 0x0000_0080;
+
+/// UBX-RXM-PMREQ message: request a power management task, eg backup mode
+/// See 32.18.3 UBX-RXM-PMREQ (0x02 0x41), version 0 with wakeup sources
+/// The device doesn't acknowledge this message.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RxmPmReqM8 {
+    pub duration_ms: u32, //4 duration - Duration of the task (ms), or 0 for until woken
+    pub flags: u32,       //8 flags - see PMREQ_FLAGS_*
+    pub wakeup_sources: u32, //12 wakeupSources - see PMREQ_WAKEUP_*
+}
+
+impl RxmPmReqM8 {
+    /// Enter backup mode for `duration_ms` (0 for until woken),
+    /// or until one of `wakeup_sources` fires
+    pub fn backup(duration_ms: u32, wakeup_sources: u32) -> Self {
+        Self {
+            duration_ms,
+            flags: PMREQ_FLAGS_BACKUP,
+            wakeup_sources,
+        }
+    }
+}
+
+pub const UBX_MSG_LEN_RXM_PMREQ: usize = 16;
+
+impl UbxCommand for RxmPmReqM8 {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_RXM_PMREQ;
+
+    fn write_payload(&self, buf: &mut [u8]) -> Option<usize> {
+        if buf.len() < UBX_MSG_LEN_RXM_PMREQ {
+            return None;
+        }
+        buf[..UBX_MSG_LEN_RXM_PMREQ].fill(0);
+        // version 0, followed by reserved bytes
+        write_u32(buf, 4, self.duration_ms);
+        write_u32(buf, 8, self.flags);
+        write_u32(buf, 12, self.wakeup_sources);
+        Some(UBX_MSG_LEN_RXM_PMREQ)
+    }
+}
+
+/// UBX-TIM-TP message: Time pulse time data
+/// See 32.20.4 UBX-TIM-TP (0x0D 0x01)
+/// Sent before the time pulse it describes.
+#[derive(Copy, Clone, Debug)]
+pub struct TimTpM8 {
+    pub tow_ms: u32, //0 towMS - Time pulse time of week, in the time base (ms)
+    pub tow_sub_ms: u32, //4 towSubMS - Submillisecond part of towMS (ms * 2^-32)
+    pub q_err: i32,      //8 qErr - Quantization error of the time pulse (ps)
+    pub week: u16,       //12 week - Time pulse week number, in the time base
+    pub flags: u8,       //14 flags - see accessors below
+    pub ref_info: u8, //15 refInfo - Time reference information, see accessors below
+}
+
+impl TimTpM8 {
+    /// Time base: false for GNSS time (see `time_ref_gnss`), true for UTC
+    pub fn time_base_utc(&self) -> bool {
+        self.flags & 0x01 != 0
+    }
+
+    /// UTC is available
+    pub fn utc(&self) -> bool {
+        self.flags & 0x02 != 0
+    }
+
+    /// RAIM information: 0 not available, 1 not active, 2 active
+    pub fn raim(&self) -> u8 {
+        (self.flags >> 2) & 0x03
+    }
+
+    /// Quantization error is valid
+    pub fn q_err_valid(&self) -> bool {
+        self.flags & 0x10 == 0
+    }
+
+    /// GNSS reference of the time base, if it's GNSS time:
+    /// 0 GPS, 1 GLONASS, 2 BeiDou, 3 Galileo, 15 unknown
+    pub fn time_ref_gnss(&self) -> u8 {
+        self.ref_info & 0x0F
+    }
+
+    /// UTC standard identifier, if the time base is UTC:
+    /// 0 not available, 1 CRL, 2 NIST, 3 USNO, 4 BIPM, 5 EU,
+    /// 6 SU, 7 NTSC, 15 unknown
+    pub fn utc_standard(&self) -> u8 {
+        (self.ref_info >> 4) & 0x0F
+    }
+}
+
+pub const UBX_MSG_LEN_TIM_TP: usize = 16;
+pub fn tim_tp_from_bytes(buf: &[u8]) -> Option<TimTpM8> {
+    if buf.len() < UBX_MSG_LEN_TIM_TP {
+        return None;
+    }
+    Some(TimTpM8 {
+        tow_ms: read_u32(buf, 0),
+        tow_sub_ms: read_u32(buf, 4),
+        q_err: read_i32(buf, 8),
+        week: read_u16(buf, 12),
+        flags: buf[14],
+        ref_info: buf[15],
+    })
+}
+
+impl UbxMessage for TimTpM8 {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_TIM_TP;
+
+    fn from_payload(buf: &[u8]) -> Option<Self> {
+        tim_tp_from_bytes(buf)
+    }
+}
+
+/// UBX-TIM-TM2 message: Time mark data, from events on an EXTINT pin
+/// See 32.20.3 UBX-TIM-TM2 (0x0D 0x03)
+#[derive(Copy, Clone, Debug)]
+pub struct TimTm2M8 {
+    pub ch: u8,     //0 ch - Channel (EXTINT pin) of the time mark: 0 or 1
+    pub flags: u8,  //1 flags - see accessors below
+    pub count: u16, //2 count - Rising edge counter
+    pub wn_r: u16,  //4 wnR - Week number of last rising edge
+    pub wn_f: u16,  //6 wnF - Week number of last falling edge
+    pub tow_ms_r: u32, //8 towMsR - Time of week of rising edge (ms)
+    pub tow_sub_ms_r: u32, //12 towSubMsR - Millisecond fraction of time of week of rising edge (ns)
+    pub tow_ms_f: u32,     //16 towMsF - Time of week of falling edge (ms)
+    pub tow_sub_ms_f: u32, //20 towSubMsF - Millisecond fraction of time of week of falling edge (ns)
+    pub acc_est: u32,      //24 accEst - Accuracy estimate (ns)
+}
+
+impl TimTm2M8 {
+    /// Mode: false for single, true for running
+    pub fn mode_running(&self) -> bool {
+        self.flags & 0x01 != 0
+    }
+
+    /// Time mark is armed
+    pub fn run(&self) -> bool {
+        self.flags & 0x02 != 0
+    }
+
+    /// A new falling edge was detected since the last message
+    pub fn new_falling_edge(&self) -> bool {
+        self.flags & 0x04 != 0
+    }
+
+    /// Time base: 0 receiver time, 1 GNSS time, 2 UTC
+    pub fn time_base(&self) -> u8 {
+        (self.flags >> 3) & 0x03
+    }
+
+    /// UTC is available
+    pub fn utc(&self) -> bool {
+        self.flags & 0x20 != 0
+    }
+
+    /// Time is valid (reconstructed from a fix)
+    pub fn time_valid(&self) -> bool {
+        self.flags & 0x40 != 0
+    }
+
+    /// A new rising edge was detected since the last message
+    pub fn new_rising_edge(&self) -> bool {
+        self.flags & 0x80 != 0
+    }
+}
+
+pub const UBX_MSG_LEN_TIM_TM2: usize = 28;
+pub fn tim_tm2_from_bytes(buf: &[u8]) -> Option<TimTm2M8> {
+    if buf.len() < UBX_MSG_LEN_TIM_TM2 {
+        return None;
+    }
+    Some(TimTm2M8 {
+        ch: buf[0],
+        flags: buf[1],
+        count: read_u16(buf, 2),
+        wn_r: read_u16(buf, 4),
+        wn_f: read_u16(buf, 6),
+        tow_ms_r: read_u32(buf, 8),
+        tow_sub_ms_r: read_u32(buf, 12),
+        tow_ms_f: read_u32(buf, 16),
+        tow_sub_ms_f: read_u32(buf, 20),
+        acc_est: read_u32(buf, 24),
+    })
+}
+
+impl UbxMessage for TimTm2M8 {
+    const MSG_UNIQUE_ID: u16 = UBX_MSG_ID_TIM_TM2;
+
+    fn from_payload(buf: &[u8]) -> Option<Self> {
+        tim_tm2_from_bytes(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_poll_frame() {
+        // poll UBX-CFG-RATE: no payload
+        let mut frame = [0u8; 8];
+        let frame_len = encode_ubx_frame(0x06, 0x08, &[], &mut frame);
+        assert_eq!(frame_len, Some(8));
+        assert_eq!(frame, [0xB5, 0x62, 0x06, 0x08, 0x00, 0x00, 0x0E, 0x30]);
+    }
+
+    #[test]
+    fn encode_frame_with_payload() {
+        let mut frame = [0u8; 16];
+        let frame_len =
+            encode_ubx_frame(0x06, 0x01, &[0x01, 0x07, 0x01], &mut frame);
+        assert_eq!(frame_len, Some(11));
+        assert_eq!(
+            frame[..11],
+            [
+                0xB5, 0x62, 0x06, 0x01, 0x03, 0x00, 0x01, 0x07, 0x01, 0x13,
+                0x51
+            ]
         );
-        msg_as_slice.copy_from_slice(input);
-        Some(msg)
+        // the checksum covers everything but the prelude and checksum
+        assert_eq!(ubx_checksum(&frame[2..9]), [0x13, 0x51]);
+    }
+
+    #[test]
+    fn encode_frame_too_long_for_buffer() {
+        let mut frame = [0u8; 10];
+        assert_eq!(encode_ubx_frame(0x06, 0x01, &[0; 3], &mut frame), None);
+    }
+
+    #[test]
+    fn nav_pvt_fields_at_their_offsets() {
+        let mut buf = [0u8; UBX_MSG_LEN_NAV_PVT];
+        buf[0..4].copy_from_slice(&[0x78, 0x56, 0x34, 0x12]);
+        buf[4..6].copy_from_slice(&[0xE4, 0x07]);
+        buf[20] = 3;
+        buf[23] = 12;
+        buf[24..28].copy_from_slice(&(-1_223_456_789i32).to_le_bytes());
+        buf[28..32].copy_from_slice(&374_567_890i32.to_le_bytes());
+        buf[76..78].copy_from_slice(&[0x8A, 0x00]);
+        buf[84..88].copy_from_slice(&(-1_000i32).to_le_bytes());
+        buf[88..90].copy_from_slice(&(-123i16).to_le_bytes());
+        buf[90..92].copy_from_slice(&[0x34, 0x12]);
+
+        let pvt = nav_pvt_from_bytes(&buf).unwrap();
+        assert_eq!(pvt.itow, 0x1234_5678);
+        assert_eq!(pvt.year, 2020);
+        assert_eq!(pvt.fix_type, 3);
+        assert_eq!(pvt.num_satellites, 12);
+        assert_eq!(pvt.lon, -1_223_456_789);
+        assert_eq!(pvt.lat, 374_567_890);
+        assert_eq!(pvt.pos_dop, 138);
+        assert_eq!(pvt.heading_vehicle, -1_000);
+        assert_eq!(pvt.mag_dec, -123);
+        assert_eq!(pvt.mag_accuracy, 0x1234);
+        assert!(nav_pvt_from_bytes(&buf[..UBX_MSG_LEN_NAV_PVT - 1]).is_none());
+    }
+
+    #[test]
+    fn mon_hw_fields_at_their_offsets() {
+        let mut buf = [0u8; UBX_MSG_LEN_MON_HW];
+        buf[16..18].copy_from_slice(&[0x52, 0x00]);
+        buf[18..20].copy_from_slice(&[0xFF, 0x1F]);
+        buf[20] = 2;
+        buf[28] = 0xAA;
+        buf[44] = 0xBB;
+        buf[45] = 17;
+        buf[56..60].copy_from_slice(&[0x01, 0x02, 0x03, 0x04]);
+
+        let hw = mon_hw_from_bytes(&buf).unwrap();
+        assert_eq!(hw.noise_per_ms, 82);
+        assert_eq!(hw.agc_count, 8191);
+        assert_eq!(hw.ant_status, 2);
+        assert_eq!(hw.pin_maps[0], 0xAA);
+        assert_eq!(hw.pin_maps[16], 0xBB);
+        assert_eq!(hw.jam_ind, 17);
+        assert_eq!(hw.pull_low, 0x0403_0201);
+        assert!(mon_hw_from_bytes(&buf[..UBX_MSG_LEN_MON_HW - 1]).is_none());
+    }
+
+    #[test]
+    fn nav_dop_fields_at_their_offsets() {
+        let buf = [
+            0x10, 0x00, 0x00, 0x00, 0x01, 0x01, 0x02, 0x01, 0x03, 0x01, 0x04,
+            0x01, 0x05, 0x01, 0x06, 0x01, 0x07, 0x01,
+        ];
+        let dop = nav_dop_from_bytes(&buf).unwrap();
+        assert_eq!(dop.itow, 16);
+        assert_eq!(dop.g_dop, 0x0101);
+        assert_eq!(dop.p_dop, 0x0102);
+        assert_eq!(dop.e_dop, 0x0107);
+        assert!(nav_dop_from_bytes(&buf[..17]).is_none());
+    }
+
+    #[test]
+    fn nav_sat_parses_each_sv_block() {
+        // header with 2 SVs, then GPS SV 5 and GLONASS SV 65
+        let buf = [
+            0x10, 0x27, 0x00, 0x00, 0x01, 0x02, 0x00, 0x00, 0x00, 0x05, 0x2A,
+            0x1E, 0x0E, 0x01, 0xF6, 0xFF, 0x1F, 0x00, 0x00, 0x00, 0x06, 0x41,
+            0x00, 0xA6, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+        ];
+        let sat = nav_sat_from_bytes(&buf).unwrap();
+        assert_eq!(sat.itow, 10_000);
+        assert_eq!(sat.num_svs, 2);
+        assert_eq!(sat.svs().len(), 2);
+        let sv = sat.svs()[0];
+        assert_eq!((sv.gnss_id, sv.sv_id, sv.cno), (0, 5, 42));
+        assert_eq!((sv.elev, sv.azim, sv.pr_res), (30, 270, -10));
+        assert_eq!(sv.quality_ind(), 7);
+        assert!(sv.sv_used());
+        assert_eq!(sv.health(), 1);
+        assert!(!sv.diff_corr());
+        let sv = sat.svs()[1];
+        assert_eq!((sv.gnss_id, sv.sv_id, sv.elev), (6, 65, -90));
+        assert!(!sv.sv_used());
+        // fewer blocks than numSvs claims
+        assert!(nav_sat_from_bytes(&buf[..buf.len() - 1]).is_none());
+    }
+
+    #[test]
+    fn nav_status_fields_at_their_offsets() {
+        let buf = [
+            0x10, 0x27, 0x00, 0x00, 0x03, 0x0D, 0x02, 0x89, 0xE8, 0x03, 0x00,
+            0x00, 0x60, 0xEA, 0x00, 0x00,
+        ];
+        let status = nav_status_from_bytes(&buf).unwrap();
+        assert_eq!(status.itow, 10_000);
+        assert_eq!(status.fix_type, 3);
+        assert!(status.gps_fix_ok());
+        assert!(!status.diff_soln());
+        assert!(status.week_set());
+        assert!(status.tow_set());
+        assert!(!status.diff_corr());
+        assert!(status.carr_soln_valid());
+        assert_eq!(status.psm_state(), 1);
+        assert_eq!(status.spoof_det_state(), 1);
+        assert_eq!(status.carr_soln(), 2);
+        assert_eq!(status.ttff, 1_000);
+        assert_eq!(status.msss, 60_000);
+        assert!(nav_status_from_bytes(&buf[..15]).is_none());
+    }
+
+    #[test]
+    fn ack_names_the_acknowledged_message() {
+        let ack = ack_from_bytes(true, &[0x06, 0x08]).unwrap();
+        assert!(ack.acknowledged);
+        assert_eq!(ack.msg_unique_id(), UBX_MSG_ID_CFG_RATE);
+        let nak = ack_from_bytes(false, &[0x06, 0x01]).unwrap();
+        assert!(!nak.acknowledged);
+        assert_eq!(nak.msg_unique_id(), UBX_MSG_ID_CFG_MSG);
+        assert!(ack_from_bytes(true, &[0x06]).is_none());
+    }
+
+    #[test]
+    fn mon_ver_keeps_version_and_extension_strings() {
+        let mut buf = [0u8; UBX_MSG_LEN_MON_VER + UBX_MSG_LEN_MON_VER_EXT];
+        buf[0..10].copy_from_slice(b"ROM CORE 3");
+        buf[30..38].copy_from_slice(b"00080000");
+        buf[40..51].copy_from_slice(b"PROTVER=18.");
+        let ver = mon_ver_from_bytes(&buf).unwrap();
+        assert_eq!(ver.sw_version(), "ROM CORE 3");
+        assert_eq!(ver.hw_version(), "00080000");
+        assert_eq!(ver.extension(0), Some("PROTVER=18."));
+        assert_eq!(ver.extension(1), None);
+        assert!(mon_ver_from_bytes(&buf[..UBX_MSG_LEN_MON_VER - 1]).is_none());
+    }
+
+    #[test]
+    fn tim_tp_fields_at_their_offsets() {
+        let buf = [
+            0x10, 0x27, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0xF4, 0xFF, 0xFF,
+            0xFF, 0xE4, 0x07, 0x07, 0x31,
+        ];
+        let tp = tim_tp_from_bytes(&buf).unwrap();
+        assert_eq!(tp.tow_ms, 10_000);
+        assert_eq!(tp.tow_sub_ms, 0x8000_0000);
+        assert_eq!(tp.q_err, -12);
+        assert_eq!(tp.week, 2020);
+        assert!(tp.time_base_utc());
+        assert!(tp.utc());
+        assert_eq!(tp.raim(), 1);
+        assert!(tp.q_err_valid());
+        assert_eq!(tp.time_ref_gnss(), 1);
+        assert_eq!(tp.utc_standard(), 3);
+        assert!(tim_tp_from_bytes(&buf[..15]).is_none());
+    }
+
+    #[test]
+    fn tim_tm2_fields_at_their_offsets() {
+        let buf = [
+            0x01, 0xCB, 0x05, 0x00, 0xE4, 0x07, 0xE3, 0x07, 0x10, 0x27, 0x00,
+            0x00, 0xE8, 0x03, 0x00, 0x00, 0x20, 0x4E, 0x00, 0x00, 0xD0, 0x07,
+            0x00, 0x00, 0x14, 0x00, 0x00, 0x00,
+        ];
+        let tm2 = tim_tm2_from_bytes(&buf).unwrap();
+        assert_eq!(tm2.ch, 1);
+        assert_eq!(tm2.count, 5);
+        assert_eq!((tm2.wn_r, tm2.wn_f), (2020, 2019));
+        assert_eq!((tm2.tow_ms_r, tm2.tow_sub_ms_r), (10_000, 1_000));
+        assert_eq!((tm2.tow_ms_f, tm2.tow_sub_ms_f), (20_000, 2_000));
+        assert_eq!(tm2.acc_est, 20);
+        assert!(tm2.mode_running());
+        assert!(tm2.run());
+        assert!(!tm2.new_falling_edge());
+        assert_eq!(tm2.time_base(), 1);
+        assert!(!tm2.utc());
+        assert!(tm2.time_valid());
+        assert!(tm2.new_rising_edge());
+        assert!(tim_tm2_from_bytes(&buf[..27]).is_none());
     }
 }
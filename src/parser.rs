@@ -0,0 +1,352 @@
+/*
+Copyright (c) 2020 Todd Stellanova
+LICENSE: BSD3 (see LICENSE file)
+*/
+
+use crate::messages::{UBX_CKSUM_LEN, UBX_PRELUDE_BYTES};
+
+/// Longest payload we skip byte-for-byte: a longer length is most
+/// likely a corrupt header, so we search for the next prelude instead.
+/// This allows for UBX-RXM-RAWX with 255 measurements (8176 bytes).
+const UBX_MAX_SKIP_LEN: usize = 8192;
+
+/// Which field of a UBX frame the parser expects next
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum ParseState {
+    /// First prelude (sync) byte
+    Sync1,
+    /// Second prelude (sync) byte
+    Sync2,
+    /// Message class
+    Class,
+    /// Message ID
+    Id,
+    /// Low byte of the little-endian payload length
+    LengthLow,
+    /// High byte of the little-endian payload length
+    LengthHigh,
+    /// Payload bytes
+    Payload,
+    /// First checksum byte
+    ChecksumA,
+    /// Second checksum byte
+    ChecksumB,
+    /// Remaining bytes of a frame too long for our buffer
+    Skip,
+}
+
+/// Header of a complete, checksum-verified UBX frame
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct UbxFrameHeader {
+    /// Message class
+    pub class: u8,
+    /// Message ID
+    pub id: u8,
+    /// Length of the payload only, in bytes
+    pub payload_len: usize,
+}
+
+impl UbxFrameHeader {
+    /// The combined class and message ID, eg 0x0107 for UBX-NAV-PVT
+    pub fn msg_unique_id(&self) -> u16 {
+        (self.class as u16) << 8 | (self.id as u16)
+    }
+}
+
+/// A resumable UBX frame parser that consumes bytes one at a time.
+///
+/// Partial frames are kept across calls, so bytes may be fed in whatever
+/// chunks they arrive in, eg from a UART interrupt or DMA callback.
+/// Bytes that are not part of a UBX frame (such as NMEA sentences)
+/// are discarded, as are frames with a bad checksum.
+/// `N` is the largest payload the parser can hold: longer frames
+/// are skipped byte-for-byte (without verifying their checksum),
+/// so that the parser stays in sync with the stream: the header of
+/// the last skipped frame is available from `take_skipped`.
+/// Frames announcing an implausibly long payload are discarded.
+pub struct UbxParser<const N: usize> {
+    state: ParseState,
+    class: u8,
+    id: u8,
+    payload_len: usize,
+    payload_idx: usize,
+    /// Bytes remaining in a frame we're skipping
+    skip_remaining: usize,
+    /// Header of the last frame skipped for being too long
+    skipped: Option<UbxFrameHeader>,
+    /// Running checksum over class, id, length and payload
+    checksum: [u8; UBX_CKSUM_LEN],
+    payload_buf: [u8; N],
+}
+
+impl<const N: usize> Default for UbxParser<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> UbxParser<N> {
+    pub fn new() -> Self {
+        Self {
+            state: ParseState::Sync1,
+            class: 0,
+            id: 0,
+            payload_len: 0,
+            payload_idx: 0,
+            skip_remaining: 0,
+            skipped: None,
+            checksum: [0; UBX_CKSUM_LEN],
+            payload_buf: [0; N],
+        }
+    }
+
+    /// Discard any partial frame and search for the next prelude
+    pub fn reset(&mut self) {
+        self.state = ParseState::Sync1;
+        self.skipped = None;
+    }
+
+    /// Header of the last frame that was too long for our buffer,
+    /// if any was skipped since the previous call.
+    /// Its `payload_len` is the length announced by the frame.
+    pub fn take_skipped(&mut self) -> Option<UbxFrameHeader> {
+        self.skipped.take()
+    }
+
+    /// Payload of the most recently completed frame.
+    /// This is only valid until the next byte is pushed.
+    pub fn payload(&self) -> &[u8] {
+        &self.payload_buf[..self.payload_len]
+    }
+
+    /// Consume bytes from `input` until a frame is complete.
+    /// Returns the number of bytes consumed, and the header of the
+    /// completed frame, if any. Bytes after a completed frame are not
+    /// consumed, so that the frame's payload can be handled first.
+    pub fn push_slice(
+        &mut self,
+        input: &[u8],
+    ) -> (usize, Option<UbxFrameHeader>) {
+        for (idx, byte) in input.iter().enumerate() {
+            if let Some(header) = self.push(*byte) {
+                return (idx + 1, Some(header));
+            }
+        }
+        (input.len(), None)
+    }
+
+    /// Consume a single byte.
+    /// Returns the header of a frame if this byte completes a valid frame:
+    /// the frame's payload is then available from `payload`.
+    pub fn push(&mut self, byte: u8) -> Option<UbxFrameHeader> {
+        match self.state {
+            ParseState::Sync1 => {
+                // anything else, such as NMEA or SPI idle filler, is skipped
+                if byte == UBX_PRELUDE_BYTES[0] {
+                    self.state = ParseState::Sync2;
+                }
+            }
+            ParseState::Sync2 => {
+                if byte == UBX_PRELUDE_BYTES[1] {
+                    self.checksum = [0; UBX_CKSUM_LEN];
+                    self.state = ParseState::Class;
+                } else if byte != UBX_PRELUDE_BYTES[0] {
+                    self.state = ParseState::Sync1;
+                }
+            }
+            ParseState::Class => {
+                self.update_checksum(byte);
+                self.class = byte;
+                self.state = ParseState::Id;
+            }
+            ParseState::Id => {
+                self.update_checksum(byte);
+                self.id = byte;
+                self.state = ParseState::LengthLow;
+            }
+            ParseState::LengthLow => {
+                self.update_checksum(byte);
+                self.payload_len = byte as usize;
+                self.state = ParseState::LengthHigh;
+            }
+            ParseState::LengthHigh => {
+                self.update_checksum(byte);
+                self.payload_len |= (byte as usize) << 8;
+                self.payload_idx = 0;
+                self.state = if self.payload_len > UBX_MAX_SKIP_LEN {
+                    // corrupt length: look for the next frame instead
+                    self.payload_len = 0;
+                    ParseState::Sync1
+                } else if self.payload_len > N {
+                    // too long for our buffer: skip the payload and checksum
+                    self.skip_remaining = self.payload_len + UBX_CKSUM_LEN;
+                    self.skipped = Some(UbxFrameHeader {
+                        class: self.class,
+                        id: self.id,
+                        payload_len: self.payload_len,
+                    });
+                    self.payload_len = 0;
+                    ParseState::Skip
+                } else if self.payload_len == 0 {
+                    ParseState::ChecksumA
+                } else {
+                    ParseState::Payload
+                };
+            }
+            ParseState::Payload => {
+                self.update_checksum(byte);
+                self.payload_buf[self.payload_idx] = byte;
+                self.payload_idx += 1;
+                if self.payload_idx == self.payload_len {
+                    self.state = ParseState::ChecksumA;
+                }
+            }
+            ParseState::ChecksumA => {
+                if byte == self.checksum[0] {
+                    self.state = ParseState::ChecksumB;
+                } else {
+                    // the failing byte may start the next frame
+                    self.resync(byte);
+                }
+            }
+            ParseState::ChecksumB => {
+                if byte == self.checksum[1] {
+                    self.state = ParseState::Sync1;
+                    return Some(UbxFrameHeader {
+                        class: self.class,
+                        id: self.id,
+                        payload_len: self.payload_len,
+                    });
+                }
+                self.resync(byte);
+            }
+            ParseState::Skip => {
+                self.skip_remaining -= 1;
+                if self.skip_remaining == 0 {
+                    self.state = ParseState::Sync1;
+                }
+            }
+        }
+        None
+    }
+
+    /// Search for the next prelude, starting with `byte`
+    fn resync(&mut self, byte: u8) {
+        self.state = if byte == UBX_PRELUDE_BYTES[0] {
+            ParseState::Sync2
+        } else {
+            ParseState::Sync1
+        };
+    }
+
+    /// 8-bit Fletcher checksum, updated one byte at a time
+    fn update_checksum(&mut self, byte: u8) {
+        self.checksum[0] = self.checksum[0].wrapping_add(byte);
+        self.checksum[1] = self.checksum[1].wrapping_add(self.checksum[0]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::encode_ubx_frame;
+
+    /// A UBX-ACK-ACK frame for UBX-CFG-RATE
+    fn ack_frame(frame: &mut [u8]) -> usize {
+        encode_ubx_frame(0x05, 0x01, &[0x06, 0x08], frame).unwrap()
+    }
+
+    #[test]
+    fn frame_split_across_push_slice_calls() {
+        let mut frame = [0u8; 16];
+        let frame_len = ack_frame(&mut frame);
+        let mut parser = UbxParser::<16>::new();
+
+        let (consumed, header) = parser.push_slice(&frame[..5]);
+        assert_eq!((consumed, header), (5, None));
+        let (consumed, header) = parser.push_slice(&frame[5..frame_len]);
+        assert_eq!(consumed, frame_len - 5);
+        let header = header.unwrap();
+        assert_eq!(header.msg_unique_id(), 0x0501);
+        assert_eq!(parser.payload(), &[0x06, 0x08]);
+    }
+
+    #[test]
+    fn bytes_after_frame_are_not_consumed() {
+        let mut stream = [0u8; 32];
+        let first_len = ack_frame(&mut stream);
+        let second_len = ack_frame(&mut stream[first_len..]);
+        let mut parser = UbxParser::<16>::new();
+
+        let (consumed, header) =
+            parser.push_slice(&stream[..first_len + second_len]);
+        assert_eq!(consumed, first_len);
+        assert!(header.is_some());
+    }
+
+    #[test]
+    fn resync_after_bad_checksum() {
+        let mut stream = [0u8; 32];
+        let bad_len = ack_frame(&mut stream);
+        stream[bad_len - 1] ^= 0xFF;
+        let good_len = ack_frame(&mut stream[bad_len..]);
+        let mut parser = UbxParser::<16>::new();
+
+        let (consumed, header) = parser.push_slice(&stream[..bad_len]);
+        assert_eq!((consumed, header), (bad_len, None));
+        let (_, header) =
+            parser.push_slice(&stream[bad_len..bad_len + good_len]);
+        assert_eq!(header.unwrap().msg_unique_id(), 0x0501);
+    }
+
+    #[test]
+    fn oversized_frame_is_skipped_byte_for_byte() {
+        // the oversized payload contains what looks like a complete frame
+        let mut inner = [0u8; 16];
+        let inner_len = ack_frame(&mut inner);
+        let mut payload = [0u8; 24];
+        payload[4..4 + inner_len].copy_from_slice(&inner[..inner_len]);
+        let mut stream = [0u8; 64];
+        let big_len =
+            encode_ubx_frame(0x0A, 0x04, &payload, &mut stream).unwrap();
+        let good_len = ack_frame(&mut stream[big_len..]);
+        let mut parser = UbxParser::<16>::new();
+
+        let (consumed, header) = parser.push_slice(&stream[..big_len]);
+        assert_eq!((consumed, header), (big_len, None));
+        let skipped = parser.take_skipped().unwrap();
+        assert_eq!(skipped.msg_unique_id(), 0x0A04);
+        assert_eq!(skipped.payload_len, payload.len());
+        assert_eq!(parser.take_skipped(), None);
+        let (_, header) =
+            parser.push_slice(&stream[big_len..big_len + good_len]);
+        assert_eq!(header.unwrap().msg_unique_id(), 0x0501);
+    }
+
+    #[test]
+    fn resync_on_byte_failing_checksum() {
+        // a frame cut short just before its checksum, then a complete one
+        let mut stream = [0u8; 32];
+        let cut_len = ack_frame(&mut stream) - UBX_CKSUM_LEN;
+        let good_len = ack_frame(&mut stream[cut_len..]);
+        let mut parser = UbxParser::<16>::new();
+
+        let (consumed, header) =
+            parser.push_slice(&stream[..cut_len + good_len]);
+        assert_eq!(consumed, cut_len + good_len);
+        assert_eq!(header.unwrap().msg_unique_id(), 0x0501);
+    }
+
+    #[test]
+    fn implausible_length_is_not_skipped() {
+        // a corrupt header announcing a 65535 byte payload
+        let mut stream = [0u8; 32];
+        stream[..6].copy_from_slice(&[0xB5, 0x62, 0x01, 0x07, 0xFF, 0xFF]);
+        let good_len = ack_frame(&mut stream[6..]);
+        let mut parser = UbxParser::<16>::new();
+
+        let (_, header) = parser.push_slice(&stream[..6 + good_len]);
+        assert_eq!(header.unwrap().msg_unique_id(), 0x0501);
+        assert_eq!(parser.take_skipped(), None);
+    }
+}